@@ -0,0 +1,116 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use finite_fields::point::Point;
+use finite_fields::private_key::PrivateKey;
+use finite_fields::schnorr::{self, KeyPair};
+use finite_fields::secp256k1::Secp256k1Point;
+use num_bigint::BigUint;
+use std::hint::black_box;
+
+fn scalar() -> BigUint {
+    let k =
+        hex::decode("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721").unwrap();
+    BigUint::from_bytes_be(&k)
+}
+
+fn generator_multiplication(c: &mut Criterion) {
+    let g = Secp256k1Point::generator();
+    let k = scalar();
+
+    // build the lazy table outside of the measurements
+    Secp256k1Point::scale_generator(&k);
+
+    let mut group = c.benchmark_group("k * G");
+    group.bench_function("double-and-add", |b| {
+        b.iter(|| g.clone().scale_double_and_add(black_box(k.clone())))
+    });
+    group.bench_function("wNAF", |b| b.iter(|| g.clone().scale(black_box(k.clone()))));
+    group.bench_function("generator table", |b| {
+        b.iter(|| Secp256k1Point::scale_generator(black_box(&k)))
+    });
+    group.finish();
+}
+
+fn point_multiplication(c: &mut Criterion) {
+    let p = Secp256k1Point::generator().scale(BigUint::from(987654321u32));
+    let k = scalar();
+
+    let mut group = c.benchmark_group("k * P");
+    group.bench_function("double-and-add", |b| {
+        b.iter(|| p.clone().scale_double_and_add(black_box(k.clone())))
+    });
+    group.bench_function("wNAF", |b| b.iter(|| p.clone().scale(black_box(k.clone()))));
+    group.bench_function("GLV", |b| b.iter(|| p.scale_glv(black_box(&k))));
+    group.bench_function("constant-time ladder", |b| {
+        b.iter(|| p.scale_constant_time(black_box(&k)))
+    });
+    group.finish();
+}
+
+fn double_multiplication(c: &mut Criterion) {
+    let g = Secp256k1Point::generator();
+    let p = g.clone().scale(BigUint::from(987654321u32));
+    let u = scalar();
+    let v = scalar() * BigUint::from(3u32) % Secp256k1Point::n();
+
+    let mut group = c.benchmark_group("u * G + v * P");
+    group.bench_function("separate", |b| {
+        b.iter(|| g.clone().scale(black_box(u.clone())) + p.clone().scale(black_box(v.clone())))
+    });
+    group.bench_function("multi_scale", |b| {
+        b.iter(|| {
+            Point::multi_scale(&[
+                (g.clone(), black_box(u.clone())),
+                (p.clone(), black_box(v.clone())),
+            ])
+        })
+    });
+    group.bench_function("multi_scale_glv", |b| {
+        b.iter(|| {
+            Secp256k1Point::multi_scale_glv(&[
+                (g.clone(), black_box(u.clone())),
+                (p.clone(), black_box(v.clone())),
+            ])
+        })
+    });
+    group.finish();
+}
+
+fn schnorr_batch_verification(c: &mut Criterion) {
+    let keypairs = (1..=32u32)
+        .map(|i| KeyPair::new(&PrivateKey::new(&(scalar() * i % Secp256k1Point::n())).unwrap()))
+        .collect::<Vec<_>>();
+    let messages = (0..32u8).map(|i| [i; 32]).collect::<Vec<_>>();
+    let signatures = keypairs
+        .iter()
+        .zip(messages.iter())
+        .map(|(keypair, msg)| schnorr::sign(msg, keypair, &[0u8; 32]).unwrap())
+        .collect::<Vec<_>>();
+    let batch = keypairs
+        .iter()
+        .zip(messages.iter())
+        .zip(signatures.iter())
+        .map(|((keypair, msg), signature)| (keypair.public_key(), &msg[..], signature))
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("32 schnorr signatures");
+    group.bench_function("one by one", |b| {
+        b.iter(|| {
+            batch
+                .iter()
+                .all(|(public_key, msg, signature)| schnorr::verify(msg, public_key, signature))
+        })
+    });
+    group.bench_function("batch", |b| {
+        b.iter(|| schnorr::verify_batch(black_box(&batch)))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    generator_multiplication,
+    point_multiplication,
+    double_multiplication,
+    schnorr_batch_verification
+);
+criterion_main!(benches);
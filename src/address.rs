@@ -0,0 +1,474 @@
+#![allow(dead_code)]
+
+use crate::base58;
+use crate::bech32;
+use crate::error::{AddressError, Error, Result};
+use crate::hash::{hash160, sha256, Hash160};
+use crate::schnorr::XOnlyPublicKey;
+use crate::secp256k1::Secp256k1Point;
+use std::fmt;
+use std::str::FromStr;
+
+// script opcodes used by the standard outputs
+const OP_0: u8 = 0x00;
+const OP_1: u8 = 0x51;
+const OP_DUP: u8 = 0x76;
+const OP_EQUAL: u8 = 0x87;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_HASH160: u8 = 0xa9;
+const OP_CHECKSIG: u8 = 0xac;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    pub fn p2pkh_prefix(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            Network::Testnet | Network::Signet | Network::Regtest => 0x6f,
+        }
+    }
+
+    pub fn p2sh_prefix(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            Network::Testnet | Network::Signet | Network::Regtest => 0xc4,
+        }
+    }
+
+    /// Human readable part of the segwit addresses.
+    pub fn hrp(self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Testnet | Network::Signet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressType {
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+}
+
+/// What the output script commits to. A P2SH-P2WPKH address is a
+/// `ScriptHash` of the witness program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Payload {
+    PubkeyHash(Hash160),
+    ScriptHash(Hash160),
+    WitnessProgram { version: u8, program: Vec<u8> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    network: Network,
+    payload: Payload,
+}
+
+impl Address {
+    /// Pay to the hash of the SEC encoding of the public key.
+    pub fn p2pkh(public_key: &Secp256k1Point, compressed: bool, network: Network) -> Address {
+        let hash = Hash160::hash(&public_key.to_sec(compressed));
+        Address {
+            network,
+            payload: Payload::PubkeyHash(hash),
+        }
+    }
+
+    /// Pay to the hash of a redeem script.
+    pub fn p2sh(redeem_script: &[u8], network: Network) -> Address {
+        Address {
+            network,
+            payload: Payload::ScriptHash(Hash160::hash(redeem_script)),
+        }
+    }
+
+    /// P2WPKH nested in P2SH, for wallets that can't pay to segwit
+    /// addresses.
+    pub fn p2sh_p2wpkh(public_key: &Secp256k1Point, network: Network) -> Address {
+        let redeem_script = witness_script_pubkey(0, &hash160(&public_key.to_sec(true)));
+        Address::p2sh(&redeem_script, network)
+    }
+
+    pub fn p2wpkh(public_key: &Secp256k1Point, network: Network) -> Address {
+        Address {
+            network,
+            payload: Payload::WitnessProgram {
+                version: 0,
+                program: hash160(&public_key.to_sec(true)).to_vec(),
+            },
+        }
+    }
+
+    /// Pay to the SHA256 of a witness script.
+    pub fn p2wsh(witness_script: &[u8], network: Network) -> Address {
+        Address {
+            network,
+            payload: Payload::WitnessProgram {
+                version: 0,
+                program: sha256(witness_script).to_vec(),
+            },
+        }
+    }
+
+    /// Taproot output of `internal_key`, tweaked with the root of the
+    /// script tree when there is one.
+    pub fn p2tr(
+        internal_key: &XOnlyPublicKey,
+        merkle_root: Option<&[u8; 32]>,
+        network: Network,
+    ) -> Result<Address> {
+        let output_key = internal_key.tap_tweak(merkle_root)?;
+        Ok(Address {
+            network,
+            payload: Payload::WitnessProgram {
+                version: 1,
+                program: output_key.to_bytes().to_vec(),
+            },
+        })
+    }
+
+    /// Segwit address of any witness version, validating the program
+    /// length.
+    pub fn from_witness_program(version: u8, program: &[u8], network: Network) -> Result<Address> {
+        // the encoding checks the version and the program
+        bech32::encode_segwit(network.hrp(), version, program)?;
+        Ok(Address {
+            network,
+            payload: Payload::WitnessProgram {
+                version,
+                program: program.to_vec(),
+            },
+        })
+    }
+
+    /// Parses `s` checking that it belongs to `network`.
+    pub fn from_str_with_network(s: &str, network: Network) -> Result<Address> {
+        let address = Address::from_str(s)?;
+        if !address.is_valid_for_network(network) {
+            return Err(AddressError::WrongNetwork(network).into());
+        }
+
+        Ok(Address { network, ..address })
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    pub fn payload(&self) -> &Payload {
+        &self.payload
+    }
+
+    /// Testnet, signet and regtest share the base58 prefixes and testnet
+    /// and signet also the human readable part, so an address can be valid
+    /// for more than one network.
+    pub fn is_valid_for_network(&self, network: Network) -> bool {
+        match self.payload {
+            Payload::PubkeyHash(_) | Payload::ScriptHash(_) => {
+                self.network.p2pkh_prefix() == network.p2pkh_prefix()
+            }
+            Payload::WitnessProgram { .. } => self.network.hrp() == network.hrp(),
+        }
+    }
+
+    /// The standard type of the address, `None` for unknown witness
+    /// versions and lengths.
+    pub fn address_type(&self) -> Option<AddressType> {
+        match &self.payload {
+            Payload::PubkeyHash(_) => Some(AddressType::P2pkh),
+            Payload::ScriptHash(_) => Some(AddressType::P2sh),
+            Payload::WitnessProgram { version, program } => match (version, program.len()) {
+                (0, 20) => Some(AddressType::P2wpkh),
+                (0, 32) => Some(AddressType::P2wsh),
+                (1, 32) => Some(AddressType::P2tr),
+                _ => None,
+            },
+        }
+    }
+
+    /// The output script paying to this address.
+    pub fn script_pubkey(&self) -> Vec<u8> {
+        match &self.payload {
+            Payload::PubkeyHash(hash) => [
+                &[OP_DUP, OP_HASH160, 20][..],
+                hash.as_byte_array(),
+                &[OP_EQUALVERIFY, OP_CHECKSIG],
+            ]
+            .concat(),
+            Payload::ScriptHash(hash) => {
+                [&[OP_HASH160, 20][..], hash.as_byte_array(), &[OP_EQUAL]].concat()
+            }
+            Payload::WitnessProgram { version, program } => {
+                witness_script_pubkey(*version, program)
+            }
+        }
+    }
+}
+
+// OP_0 or OP_1..OP_16 followed by the push of the program
+fn witness_script_pubkey(version: u8, program: &[u8]) -> Vec<u8> {
+    let op_version = if version == 0 {
+        OP_0
+    } else {
+        OP_1 + version - 1
+    };
+    [&[op_version, program.len() as u8], program].concat()
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let encoded = match &self.payload {
+            Payload::PubkeyHash(hash) => base58::encode_check(
+                &[&[self.network.p2pkh_prefix()][..], hash.as_byte_array()].concat(),
+            ),
+            Payload::ScriptHash(hash) => base58::encode_check(
+                &[&[self.network.p2sh_prefix()][..], hash.as_byte_array()].concat(),
+            ),
+            Payload::WitnessProgram { version, program } => {
+                bech32::encode_segwit(self.network.hrp(), *version, program)
+                    .map_err(|_| fmt::Error)?
+            }
+        };
+        write!(f, "{}", encoded)
+    }
+}
+
+impl FromStr for Address {
+    type Err = Error;
+
+    /// Parses an address of any network. Base58 addresses of the test
+    /// networks come out as `Testnet`, use `from_str_with_network` to pick
+    /// between them.
+    fn from_str(s: &str) -> Result<Address> {
+        let lowercase = s.to_lowercase();
+        let segwit_network = [Network::Mainnet, Network::Testnet, Network::Regtest]
+            .into_iter()
+            .find(|network| lowercase.starts_with(&format!("{}1", network.hrp())));
+
+        if let Some(network) = segwit_network {
+            let (version, program) = bech32::decode_segwit(network.hrp(), s)?;
+            return Ok(Address {
+                network,
+                payload: Payload::WitnessProgram { version, program },
+            });
+        }
+
+        // a well formed bech32 string of some other chain
+        if let Ok((hrp, _, _)) = bech32::decode(s) {
+            return Err(AddressError::UnknownHrp(hrp).into());
+        }
+
+        let data = base58::decode_check(s)?;
+        if data.len() != 21 {
+            return Err(AddressError::InvalidLength(data.len()).into());
+        }
+
+        let hash = Hash160::from_slice(&data[1..])?;
+        let (network, payload) = match data[0] {
+            0x00 => (Network::Mainnet, Payload::PubkeyHash(hash)),
+            0x05 => (Network::Mainnet, Payload::ScriptHash(hash)),
+            0x6f => (Network::Testnet, Payload::PubkeyHash(hash)),
+            0xc4 => (Network::Testnet, Payload::ScriptHash(hash)),
+            prefix => return Err(AddressError::UnknownPrefix(prefix).into()),
+        };
+        Ok(Address { network, payload })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Bech32Error;
+
+    fn public_key(sec: &str) -> Secp256k1Point {
+        Secp256k1Point::from_sec(&hex::decode(sec).unwrap()).unwrap()
+    }
+
+    // Display, FromStr and script_pubkey
+    fn check(address: &Address, expected: &str, script_pubkey: &str) {
+        assert_eq!(address.to_string(), expected);
+        assert_eq!(
+            Address::from_str_with_network(expected, address.network()),
+            Ok(address.clone())
+        );
+        assert_eq!(hex::encode(address.script_pubkey()), script_pubkey);
+    }
+
+    #[test]
+    fn test_p2pkh() {
+        let g = Secp256k1Point::generator();
+        check(
+            &Address::p2pkh(&g, true, Network::Mainnet),
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+            "76a914751e76e8199196d454941c45d1b3a323f1433bd688ac",
+        );
+
+        let key = public_key("048d5141948c1702e8c95f438815794b87f706a8d4cd2bffad1dc1570971032c9b6042a0431ded2478b5c9cf2d81c124a5e57347a3c63ef0e7716cf54d613ba183");
+        let address = Address::p2pkh(&key, false, Network::Mainnet);
+        assert_eq!(address.to_string(), "1QJVDzdqb1VpbDK7uDeyVXy9mR27CJiyhY");
+
+        let key = public_key("03df154ebfcf29d29cc10d5c2565018bce2d9edbab267c31d2caf44a63056cf99f");
+        let address = Address::p2pkh(&key, true, Network::Testnet);
+        assert_eq!(address.to_string(), "mqkhEMH6NCeYjFybv7pvFC22MFeaNT9AQC");
+        assert_eq!(address.address_type(), Some(AddressType::P2pkh));
+
+        let address: Address = "132F25rTsvBdp9JzLLBHP5mvGY66i1xdiM".parse().unwrap();
+        assert_eq!(
+            hex::encode(address.script_pubkey()),
+            "76a914162c5ea71c0b23f5b9022ef047c4a86470a5b07088ac"
+        );
+    }
+
+    #[test]
+    fn test_p2sh() {
+        let address: Address = "33iFwdLuRpW1uK1RTRqsoi8rR4NpDzk66k".parse().unwrap();
+        assert_eq!(address.address_type(), Some(AddressType::P2sh));
+        assert_eq!(
+            hex::encode(address.script_pubkey()),
+            "a914162c5ea71c0b23f5b9022ef047c4a86470a5b07087"
+        );
+
+        let redeem_script = hex::decode("552103a765fc35b3f210b95223846b36ef62a4e53e34e2925270c2c7906b92c9f718eb2103c327511374246759ec8d0b89fa6c6b23b33e11f92c5bc155409d86de0c79180121038cae7406af1f12f4786d820a1466eec7bc5785a1b5e4a387eca6d797753ef6db2103252bfb9dcaab0cd00353f2ac328954d791270203d66c2be8b430f115f451b8a12103e79412d42372c55dd336f2eb6eb639ef9d74a22041ba79382c74da2338fe58ad21035049459a4ebc00e876a9eef02e72a3e70202d3d1f591fc0dd542f93f642021f82102016f682920d9723c61b27f562eb530c926c00106004798b6471e8c52c60ee02057ae").unwrap();
+        let address = Address::p2sh(&redeem_script, Network::Testnet);
+        assert_eq!(address.to_string(), "2N3zXjbwdTcPsJiy8sUK9FhWJhqQCxA8Jjr");
+    }
+
+    #[test]
+    fn test_p2sh_p2wpkh() {
+        let key = public_key("026c468be64d22761c30cd2f12cbc7de255d592d7904b1bab07236897cc4c2e766");
+        let address = Address::p2sh_p2wpkh(&key, Network::Mainnet);
+
+        assert_eq!(address.to_string(), "3QBRmWNqqBGme9er7fMkGqtZtp4gjMFxhE");
+        assert_eq!(address.address_type(), Some(AddressType::P2sh));
+    }
+
+    #[test]
+    fn test_p2wpkh() {
+        let key = public_key("033bc8c83c52df5712229a2f72206d90192366c36428cb0c12b6af98324d97bfbc");
+        let address = Address::p2wpkh(&key, Network::Mainnet);
+
+        assert_eq!(address.address_type(), Some(AddressType::P2wpkh));
+        check(
+            &address,
+            "bc1qvzvkjn4q3nszqxrv3nraga2r822xjty3ykvkuw",
+            &format!("0014{}", hex::encode(hash160(&key.to_sec(true)))),
+        );
+    }
+
+    #[test]
+    fn test_p2wsh() {
+        let witness_script = hex::decode("52210375e00eb72e29da82b89367947f29ef34afb75e8654f6ea368e0acdfd92976b7c2103a1b26313f430c4b15bb1fdce663207659d8cac749a0e53d70eff01874496feff2103c96d495bfdd5ba4145e3e046fee45e84a8a48ad05bd8dbb395c011a32cf9f88053ae").unwrap();
+        let address = Address::p2wsh(&witness_script, Network::Mainnet);
+
+        assert_eq!(address.address_type(), Some(AddressType::P2wsh));
+        assert_eq!(
+            address.to_string(),
+            "bc1qwqdg6squsna38e46795at95yu9atm8azzmyvckulcc7kytlcckxswvvzej"
+        );
+        assert_eq!(
+            "bc1qwqdg6squsna38e46795at95yu9atm8azzmyvckulcc7kytlcckxswvvzej".parse(),
+            Ok(address)
+        );
+    }
+
+    #[test]
+    fn test_p2tr() {
+        // BIP86 key m/86'/0'/0'/0/0
+        let internal_key = XOnlyPublicKey::from_bytes(
+            &hex::decode("cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115")
+                .unwrap(),
+        )
+        .unwrap();
+        let address = Address::p2tr(&internal_key, None, Network::Mainnet).unwrap();
+
+        assert_eq!(address.address_type(), Some(AddressType::P2tr));
+        check(
+            &address,
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+            "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+        );
+    }
+
+    #[test]
+    fn test_networks() {
+        let g = Secp256k1Point::generator();
+        let testnet = Address::p2wpkh(&g, Network::Testnet).to_string();
+        let regtest = Address::p2wpkh(&g, Network::Regtest).to_string();
+        assert!(testnet.starts_with("tb1q"));
+        assert!(regtest.starts_with("bcrt1q"));
+
+        let signet = Address::from_str_with_network(&testnet, Network::Signet).unwrap();
+        assert_eq!(signet.network(), Network::Signet);
+        assert_eq!(
+            Address::from_str_with_network(&testnet, Network::Regtest),
+            Err(AddressError::WrongNetwork(Network::Regtest).into())
+        );
+        assert_eq!(
+            regtest.parse::<Address>().map(|address| address.network()),
+            Ok(Network::Regtest)
+        );
+
+        let legacy = Address::p2pkh(&g, true, Network::Regtest).to_string();
+        assert!(Address::from_str_with_network(&legacy, Network::Testnet).is_ok());
+        assert_eq!(
+            Address::from_str_with_network(&legacy, Network::Mainnet),
+            Err(AddressError::WrongNetwork(Network::Mainnet).into())
+        );
+    }
+
+    #[test]
+    fn test_from_witness_program() {
+        let program = [0x75u8; 40];
+        let address = Address::from_witness_program(13, &program, Network::Mainnet).unwrap();
+
+        assert_eq!(address.address_type(), None);
+        assert_eq!(address.to_string().parse(), Ok(address));
+        assert_eq!(
+            Address::from_witness_program(0, &program, Network::Mainnet),
+            Err(Bech32Error::InvalidProgramLength {
+                version: 0,
+                length: 40
+            }
+            .into())
+        );
+    }
+
+    #[test]
+    fn test_invalid_addresses() {
+        // checksum
+        assert!(matches!(
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMJ".parse::<Address>(),
+            Err(Error::InvalidBase58(_))
+        ));
+        assert!(matches!(
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5".parse::<Address>(),
+            Err(Error::InvalidBech32(Bech32Error::InvalidChecksum(_)))
+        ));
+
+        // unknown networks
+        assert_eq!(
+            "tc1qw508d6qejxtdg4y5r3zarvary0c5xw7kg3g4ty".parse::<Address>(),
+            Err(AddressError::UnknownHrp("tc".to_string()).into())
+        );
+        let unknown_prefix = base58::encode_check(&[0x30; 21]);
+        assert_eq!(
+            unknown_prefix.parse::<Address>(),
+            Err(AddressError::UnknownPrefix(0x30).into())
+        );
+        assert_eq!(
+            base58::encode_check(&[0x00; 33]).parse::<Address>(),
+            Err(AddressError::InvalidLength(33).into())
+        );
+    }
+}
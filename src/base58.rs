@@ -0,0 +1,185 @@
+#![allow(dead_code)]
+
+use crate::error::{Base58Error, Result};
+use crate::hash::sha256_double;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+// Digit value of every ASCII character, -1 for the ones outside the alphabet
+const DIGITS: [i8; 128] = {
+    let mut digits = [-1i8; 128];
+    let mut i = 0;
+    while i < ALPHABET.len() {
+        digits[ALPHABET[i] as usize] = i as i8;
+        i += 1;
+    }
+    digits
+};
+
+/// Base58 with Bitcoin's alphabet. Every leading zero byte becomes a '1'.
+pub fn encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|&&byte| byte == 0).count();
+
+    // little-endian base 58 digits of the rest of the number
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for &byte in &data[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    std::iter::repeat_n('1', zeros)
+        .chain(digits.iter().rev().map(|&d| ALPHABET[d as usize] as char))
+        .collect()
+}
+
+pub fn decode(s: &str) -> Result<Vec<u8>> {
+    let zeros = s.chars().take_while(|&c| c == '1').count();
+
+    // little-endian bytes of the number
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len() * 733 / 1000 + 1);
+    for (position, character) in s.chars().enumerate().skip(zeros) {
+        let digit = match character {
+            c if c.is_ascii() && DIGITS[c as usize] >= 0 => DIGITS[c as usize] as u32,
+            _ => {
+                return Err(Base58Error::InvalidCharacter {
+                    character,
+                    position,
+                }
+                .into())
+            }
+        };
+
+        let mut carry = digit;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    Ok(std::iter::repeat_n(0, zeros)
+        .chain(bytes.into_iter().rev())
+        .collect())
+}
+
+fn checksum(data: &[u8]) -> [u8; 4] {
+    let hash = sha256_double(data);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Base58Check: base58 of the data followed by the first 4 bytes of its
+/// `sha256_double`.
+pub fn encode_check(data: &[u8]) -> String {
+    encode(&[data, &checksum(data)].concat())
+}
+
+/// Decodes a Base58Check string, returning the data without the checksum.
+pub fn decode_check(s: &str) -> Result<Vec<u8>> {
+    let mut data = decode(s)?;
+    if data.len() < 4 {
+        return Err(Base58Error::TooShort(data.len()).into());
+    }
+
+    let found = data.split_off(data.len() - 4);
+    let expected = checksum(&data);
+    if found != expected {
+        return Err(Base58Error::InvalidChecksum {
+            expected: u32::from_be_bytes(expected),
+            found: u32::from_be_bytes(found.try_into().unwrap()),
+        }
+        .into());
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    // https://github.com/bitcoin/bitcoin/blob/master/src/test/data/base58_encode_decode.json
+    const TEST_VECTORS: &str = include_str!("../test_vectors/base58_encode_decode.json");
+
+    #[test]
+    fn test_base58_encode_decode_vectors() {
+        let vectors: Vec<(String, String)> = serde_json::from_str(TEST_VECTORS).unwrap();
+
+        for (data, expected) in vectors {
+            let data = hex::decode(data).unwrap();
+
+            assert_eq!(encode(&data), expected);
+            assert_eq!(decode(&expected).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_decode_invalid_character() {
+        for (s, character, position) in [("0", '0', 0), ("3EFU7l", 'l', 5), ("1IO", 'I', 1)] {
+            assert_eq!(
+                decode(s),
+                Err(Error::InvalidBase58(Base58Error::InvalidCharacter {
+                    character,
+                    position
+                }))
+            );
+        }
+        assert_eq!(
+            decode("2g¢"),
+            Err(Error::InvalidBase58(Base58Error::InvalidCharacter {
+                character: '¢',
+                position: 2
+            }))
+        );
+        assert!(decode(" 2g").is_err());
+    }
+
+    #[test]
+    fn test_base58check() {
+        // P2PKH address of the hash160 00f8917303bfa8ef24f292e8fa1419b20460ba064d
+        let payload = hex::decode("00f8917303bfa8ef24f292e8fa1419b20460ba064d").unwrap();
+        let address = "1PfJpZsjreyVrqeoAfabrRwwjQyoSQMmHH";
+
+        assert_eq!(encode_check(&payload), address);
+        assert_eq!(decode_check(address).unwrap(), payload);
+        assert_eq!(decode_check(&encode_check(&[])).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_base58check_errors() {
+        assert_eq!(
+            decode_check(&encode(&[1, 2, 3])),
+            Err(Error::InvalidBase58(Base58Error::TooShort(3)))
+        );
+
+        // last character changed
+        assert!(matches!(
+            decode_check("1PfJpZsjreyVrqeoAfabrRwwjQyoSQMmHJ"),
+            Err(Error::InvalidBase58(Base58Error::InvalidChecksum { .. }))
+        ));
+
+        let payload = [0u8; 21];
+        let mut data = payload.to_vec();
+        data.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        let expected = u32::from_be_bytes(checksum(&payload));
+        assert_eq!(
+            decode_check(&encode(&data)),
+            Err(Error::InvalidBase58(Base58Error::InvalidChecksum {
+                expected,
+                found: 0xdeadbeef
+            }))
+        );
+    }
+}
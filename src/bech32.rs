@@ -0,0 +1,633 @@
+#![allow(dead_code)]
+
+use crate::error::{Bech32Error, Result};
+use std::collections::HashMap;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+const CHECKSUM_LENGTH: usize = 6;
+const MAX_LENGTH: usize = 90;
+
+/// BIP173 bech32 or its BIP350 successor bech32m. They only differ in the
+/// constant the checksum is xored with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc830a3,
+        }
+    }
+}
+
+// One step of the checksum: multiplies the residue by x and adds `value`.
+fn polymod_step(chk: u32, value: u8) -> u32 {
+    let top = chk >> 25;
+    let mut chk = ((chk & 0x1ffffff) << 5) ^ value as u32;
+    for (i, generator) in GENERATOR.iter().enumerate() {
+        if (top >> i) & 1 == 1 {
+            chk ^= generator;
+        }
+    }
+    chk
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    values
+        .iter()
+        .fold(1, |chk, &value| polymod_step(chk, value))
+}
+
+// High bits of every character, a zero and then the low bits
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    hrp.bytes()
+        .map(|c| c >> 5)
+        .chain([0])
+        .chain(hrp.bytes().map(|c| c & 31))
+        .collect()
+}
+
+fn create_checksum(hrp: &str, data: &[u8], variant: Variant) -> [u8; CHECKSUM_LENGTH] {
+    let values = [&hrp_expand(hrp), data, &[0; CHECKSUM_LENGTH]].concat();
+    let residue = polymod(&values) ^ variant.constant();
+
+    let mut checksum = [0u8; CHECKSUM_LENGTH];
+    for (i, value) in checksum.iter_mut().enumerate() {
+        *value = ((residue >> (5 * (5 - i))) & 31) as u8;
+    }
+    checksum
+}
+
+/// Encodes the 5-bit `data` values under the human readable part `hrp`.
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> Result<String> {
+    if hrp.is_empty() || hrp.len() > 83 || hrp.bytes().any(|c| !(33..=126).contains(&c)) {
+        return Err(Bech32Error::InvalidHrp.into());
+    }
+    if let Some(&value) = data.iter().find(|&&value| value > 31) {
+        return Err(Bech32Error::InvalidDataValue(value).into());
+    }
+
+    let length = hrp.len() + 1 + data.len() + CHECKSUM_LENGTH;
+    if length > MAX_LENGTH {
+        return Err(Bech32Error::InvalidLength(length).into());
+    }
+
+    let hrp = hrp.to_lowercase();
+    let checksum = create_checksum(&hrp, data, variant);
+    let data = data
+        .iter()
+        .chain(checksum.iter())
+        .map(|&value| CHARSET[value as usize] as char);
+
+    Ok(format!("{}1{}", hrp, data.collect::<String>()))
+}
+
+/// Decodes a bech32 or bech32m string into its lowercase human readable
+/// part and the 5-bit data values without the checksum.
+///
+/// When the checksum doesn't match, the error lists the positions of up to
+/// two substituted characters that would make it valid, or nothing when
+/// the string has more errors than that.
+pub fn decode(s: &str) -> Result<(String, Vec<u8>, Variant)> {
+    let (hrp, mut values) = parse(s)?;
+
+    let residue = polymod(&[&hrp_expand(&hrp), &values[..]].concat());
+    let variant = [Variant::Bech32, Variant::Bech32m]
+        .into_iter()
+        .find(|variant| residue == variant.constant())
+        .ok_or_else(|| {
+            Bech32Error::InvalidChecksum(locate_errors(&hrp, &values, Variant::Bech32))
+        })?;
+
+    values.truncate(values.len() - CHECKSUM_LENGTH);
+    Ok((hrp, values, variant))
+}
+
+// Splits the string into its lowercase hrp and the data values, checksum
+// included, validating everything but the checksum.
+fn parse(s: &str) -> Result<(String, Vec<u8>)> {
+    if let Some((position, character)) = s
+        .chars()
+        .enumerate()
+        .find(|(_, c)| !(33..=126).contains(&(*c as u32)))
+    {
+        return Err(Bech32Error::InvalidCharacter {
+            character,
+            position,
+        }
+        .into());
+    }
+    if s.len() > MAX_LENGTH {
+        return Err(Bech32Error::InvalidLength(s.len()).into());
+    }
+    if s.chars().any(|c| c.is_ascii_lowercase()) && s.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err(Bech32Error::MixedCase.into());
+    }
+
+    let s = s.to_lowercase();
+    let separator = s.rfind('1').ok_or(Bech32Error::MissingSeparator)?;
+    if separator == 0 || separator > 83 {
+        return Err(Bech32Error::InvalidHrp.into());
+    }
+    if s.len() - separator - 1 < CHECKSUM_LENGTH {
+        return Err(Bech32Error::ChecksumTooShort.into());
+    }
+
+    let mut values = Vec::with_capacity(s.len() - separator - 1);
+    for (position, c) in s.bytes().enumerate().skip(separator + 1) {
+        match CHARSET.iter().position(|&d| d == c) {
+            Some(value) => values.push(value as u8),
+            None => {
+                return Err(Bech32Error::InvalidCharacter {
+                    character: c as char,
+                    position,
+                }
+                .into())
+            }
+        }
+    }
+
+    Ok((s[..separator].to_string(), values))
+}
+
+/// Positions in the string of up to two substituted data characters that
+/// explain a wrong checksum. Both variants are tried and the shortest
+/// explanation wins, `preferred` breaking the ties.
+///
+/// The checksum is an affine function of the values, so a substitution of
+/// `e` at position `i` changes the residue by a syndrome that only depends
+/// on `e` and on how many values follow `i`. The code has distance 5 for
+/// strings up to 90 characters, so one or two syndromes adding up to the
+/// residue identify the errors unambiguously.
+fn locate_errors(hrp: &str, values: &[u8], preferred: Variant) -> Vec<usize> {
+    let offset = hrp.len() + 1;
+    let n = values.len();
+
+    // syndrome of adding e at data position i
+    let mut syndromes = HashMap::with_capacity(n * 31);
+    for e in 1..32u8 {
+        let mut syndrome = e as u32;
+        for i in (0..n).rev() {
+            syndromes.insert(syndrome, i);
+            syndrome = polymod_step(syndrome, 0);
+        }
+    }
+
+    let other = match preferred {
+        Variant::Bech32 => Variant::Bech32m,
+        Variant::Bech32m => Variant::Bech32,
+    };
+    let residue = polymod(&[&hrp_expand(hrp), values].concat());
+    [preferred, other]
+        .into_iter()
+        .filter_map(|variant| {
+            let residue = residue ^ variant.constant();
+            if let Some(&i) = syndromes.get(&residue) {
+                return Some(vec![offset + i]);
+            }
+            syndromes.iter().find_map(
+                |(syndrome, &i)| match syndromes.get(&(residue ^ syndrome)) {
+                    Some(&j) if i < j => Some(vec![offset + i, offset + j]),
+                    _ => None,
+                },
+            )
+        })
+        .min_by_key(|positions| positions.len())
+        .unwrap_or_default()
+}
+
+/// Regroups a stream of `from`-bit values into `to`-bit values. With `pad`
+/// the last group is completed with zeros; without it the leftover bits
+/// must be fewer than `from` and all zero.
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max = (1u32 << to) - 1;
+    let mut result = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+
+    for &value in data {
+        if (value as u32) >> from != 0 {
+            return Err(Bech32Error::InvalidDataValue(value).into());
+        }
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            result.push(((acc >> bits) & max) as u8);
+        }
+    }
+
+    if pad {
+        if bits > 0 {
+            result.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return Err(Bech32Error::InvalidPadding.into());
+    }
+
+    Ok(result)
+}
+
+fn check_witness_program(version: u8, program: &[u8]) -> Result<()> {
+    if version > 16 {
+        return Err(Bech32Error::InvalidWitnessVersion(version).into());
+    }
+
+    let length = program.len();
+    if !(2..=40).contains(&length) || (version == 0 && length != 20 && length != 32) {
+        return Err(Bech32Error::InvalidProgramLength { version, length }.into());
+    }
+    Ok(())
+}
+
+/// Segwit address of a witness program: bech32 for version 0 and bech32m
+/// for the later versions.
+pub fn encode_segwit(hrp: &str, version: u8, program: &[u8]) -> Result<String> {
+    check_witness_program(version, program)?;
+
+    let data = [&[version], &convert_bits(program, 8, 5, true)?[..]].concat();
+    encode(hrp, &data, segwit_variant(version))
+}
+
+fn segwit_variant(version: u8) -> Variant {
+    if version == 0 {
+        Variant::Bech32
+    } else {
+        Variant::Bech32m
+    }
+}
+
+/// Decodes a segwit address for the network with human readable part `hrp`
+/// into its witness version and program.
+///
+/// The witness version selects the checksum variant, which also resolves
+/// the error positions when a typo could be explained under both.
+pub fn decode_segwit(hrp: &str, s: &str) -> Result<(u8, Vec<u8>)> {
+    let (found, values) = parse(s)?;
+    if found != hrp.to_lowercase() {
+        return Err(Bech32Error::WrongHrp {
+            expected: hrp.to_string(),
+            found,
+        }
+        .into());
+    }
+
+    if values.len() == CHECKSUM_LENGTH {
+        return Err(Bech32Error::MissingWitnessVersion.into());
+    }
+
+    let version = values[0];
+    let expected = segwit_variant(version);
+    let residue = polymod(&[&hrp_expand(&found), &values[..]].concat());
+    if residue != expected.constant() {
+        if [Variant::Bech32, Variant::Bech32m]
+            .iter()
+            .any(|v| residue == v.constant())
+        {
+            return Err(Bech32Error::WrongVariant(version).into());
+        }
+        let positions = locate_errors(&found, &values, expected);
+        return Err(Bech32Error::InvalidChecksum(positions).into());
+    }
+
+    let program = convert_bits(&values[1..values.len() - CHECKSUM_LENGTH], 5, 8, false)?;
+    check_witness_program(version, &program)?;
+
+    Ok((version, program))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::hash::hash160;
+    use crate::secp256k1::Secp256k1Point;
+
+    fn bech32_error(error: Bech32Error) -> Result<(String, Vec<u8>, Variant)> {
+        Err(Error::InvalidBech32(error))
+    }
+
+    #[test]
+    fn test_valid_strings() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki#test-vectors
+        let bech32 = [
+            "A12UEL5L",
+            "a12uel5l",
+            "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+            "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+            "11qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqc8247j",
+            "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+            "?1ezyfcl",
+        ];
+        // https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki#test-vectors
+        let bech32m = [
+            "A1LQFN3A",
+            "a1lqfn3a",
+            "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "11llllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllludsr8",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+            "?1v759aa",
+        ];
+
+        for (strings, variant) in [(bech32, Variant::Bech32), (bech32m, Variant::Bech32m)] {
+            for s in strings {
+                let (hrp, data, found) = decode(s).unwrap();
+                assert_eq!(found, variant);
+                assert_eq!(encode(&hrp, &data, variant).unwrap(), s.to_lowercase());
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_strings() {
+        let cases = [
+            (
+                " 1nwldj5",
+                Bech32Error::InvalidCharacter {
+                    character: ' ',
+                    position: 0,
+                },
+            ),
+            (
+                "\u{7f}1axkwrx",
+                Bech32Error::InvalidCharacter {
+                    character: '\u{7f}',
+                    position: 0,
+                },
+            ),
+            (
+                "\u{80}1eym55h",
+                Bech32Error::InvalidCharacter {
+                    character: '\u{80}',
+                    position: 0,
+                },
+            ),
+            (
+                "an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx",
+                Bech32Error::InvalidLength(91),
+            ),
+            ("pzry9x0s0muk", Bech32Error::MissingSeparator),
+            ("1pzry9x0s0muk", Bech32Error::InvalidHrp),
+            (
+                "x1b4n0q5v",
+                Bech32Error::InvalidCharacter {
+                    character: 'b',
+                    position: 2,
+                },
+            ),
+            ("li1dgmt3", Bech32Error::ChecksumTooShort),
+            (
+                "de1lg7wt\u{ff}",
+                Bech32Error::InvalidCharacter {
+                    character: '\u{ff}',
+                    position: 8,
+                },
+            ),
+            ("10a06t8", Bech32Error::InvalidHrp),
+            ("1qzzfhee", Bech32Error::InvalidHrp),
+            ("qyrz8wqd2c9m", Bech32Error::MissingSeparator),
+            ("1qyrz8wqd2c9m", Bech32Error::InvalidHrp),
+            (
+                "mm1crxm3i",
+                Bech32Error::InvalidCharacter {
+                    character: 'i',
+                    position: 8,
+                },
+            ),
+            (
+                "au1s5cgom",
+                Bech32Error::InvalidCharacter {
+                    character: 'o',
+                    position: 7,
+                },
+            ),
+            ("16plkw9", Bech32Error::InvalidHrp),
+            ("1p2gdwpf", Bech32Error::InvalidHrp),
+        ];
+
+        for (s, error) in cases {
+            assert_eq!(decode(s), bech32_error(error), "{}", s);
+        }
+
+        // checksums computed with the uppercase form of the hrp
+        for s in ["A1G7SGD8", "M1VUXWEZ"] {
+            assert!(matches!(
+                decode(s),
+                Err(Error::InvalidBech32(Bech32Error::InvalidChecksum(_)))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_valid_segwit_addresses() {
+        let cases = [
+            (
+                "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            (
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+            ),
+            (
+                "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y",
+                "5128751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            ("BC1SW50QGDZ25J", "6002751e"),
+            (
+                "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs",
+                "5210751e76e8199196d454941c45d1b3a323",
+            ),
+            (
+                "tb1qqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesrxh6hy",
+                "0020000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433",
+            ),
+            (
+                "tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c",
+                "5120000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433",
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            ),
+        ];
+
+        for (address, script_pubkey) in cases {
+            let hrp = &address[..2].to_lowercase();
+            let (version, program) = decode_segwit(hrp, address).unwrap();
+
+            // OP_0 or OP_1..OP_16, then a push of the program
+            let op_version = if version == 0 { 0 } else { 0x50 + version };
+            let script = [&[op_version, program.len() as u8], &program[..]].concat();
+            assert_eq!(hex::encode(script), script_pubkey);
+
+            assert_eq!(
+                encode_segwit(hrp, version, &program).unwrap(),
+                address.to_lowercase()
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_segwit_addresses() {
+        let cases = [
+            (
+                "tc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq5zuyut",
+                Bech32Error::WrongHrp {
+                    expected: "bc".to_string(),
+                    found: "tc".to_string(),
+                },
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+                Bech32Error::WrongVariant(1),
+            ),
+            (
+                "BC1S0XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ54WELL",
+                Bech32Error::WrongVariant(16),
+            ),
+            (
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+                Bech32Error::WrongVariant(0),
+            ),
+            (
+                "BC130XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ7ZWS8R",
+                Bech32Error::InvalidWitnessVersion(17),
+            ),
+            (
+                "bc1pw5dgrnzv",
+                Bech32Error::InvalidProgramLength {
+                    version: 1,
+                    length: 1,
+                },
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v8n0nx0muaewav253zgeav",
+                Bech32Error::InvalidProgramLength {
+                    version: 1,
+                    length: 41,
+                },
+            ),
+            (
+                "BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P",
+                Bech32Error::InvalidProgramLength {
+                    version: 0,
+                    length: 16,
+                },
+            ),
+            (
+                "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47Zagq",
+                Bech32Error::MixedCase,
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v07qwwzcrf",
+                Bech32Error::InvalidPadding,
+            ),
+            (
+                "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vpggkg4j",
+                Bech32Error::InvalidPadding,
+            ),
+            ("bc1gmk9yu", Bech32Error::MissingWitnessVersion),
+            (
+                "bc1p38j9r5y49hruaue7wxjce0updqjuyyx0kh56v8s25huc6995vvpql3jow4",
+                Bech32Error::InvalidCharacter {
+                    character: 'o',
+                    position: 59,
+                },
+            ),
+        ];
+
+        for (address, error) in cases {
+            let hrp = if address.to_lowercase().starts_with("tb") {
+                "tb"
+            } else {
+                "bc"
+            };
+            assert_eq!(
+                decode_segwit(hrp, address),
+                Err(Error::InvalidBech32(error)),
+                "{}",
+                address
+            );
+        }
+    }
+
+    #[test]
+    fn test_segwit_address_of_public_key() {
+        let public_key = Secp256k1Point::generator().to_sec(true);
+        let address = encode_segwit("bc", 0, &hash160(&public_key)).unwrap();
+
+        assert_eq!(address, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+    }
+
+    #[test]
+    fn test_locate_errors() {
+        // last character of "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4" replaced
+        assert_eq!(
+            decode("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"),
+            bech32_error(Bech32Error::InvalidChecksum(vec![41]))
+        );
+
+        let addresses = [
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+            "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+        ];
+
+        // replaces the character at `position` by a later one of the charset
+        let substitute = |s: &mut Vec<u8>, position: usize, shift: usize| {
+            let value = CHARSET.iter().position(|&c| c == s[position]).unwrap();
+            s[position] = CHARSET[(value + shift) % 32];
+        };
+
+        // segwit addresses go through decode_segwit, whose witness version
+        // picks the variant
+        let located = |s: &[u8]| {
+            let s = std::str::from_utf8(s).unwrap();
+            let error = match &s[..2] {
+                "bc" | "tb" => decode_segwit(&s[..2], s).unwrap_err(),
+                _ => decode(s).unwrap_err(),
+            };
+            match error {
+                Error::InvalidBech32(Bech32Error::InvalidChecksum(positions)) => positions,
+                error => panic!("unexpected error {:?}", error),
+            }
+        };
+
+        for address in addresses {
+            let data_start = address.rfind('1').unwrap() + 1;
+
+            for i in data_start..address.len() {
+                let mut s = address.as_bytes().to_vec();
+                substitute(&mut s, i, 1 + i % 31);
+                assert_eq!(located(&s), vec![i]);
+
+                for j in (i + 1..address.len()).step_by(3) {
+                    let mut s = s.clone();
+                    substitute(&mut s, j, 1 + j % 29);
+                    assert_eq!(located(&s), vec![i, j]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_convert_bits() {
+        let bytes = hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        let values = convert_bits(&bytes, 8, 5, true).unwrap();
+
+        assert_eq!(values.len(), 32);
+        assert_eq!(convert_bits(&values, 5, 8, false).unwrap(), bytes);
+        assert_eq!(
+            convert_bits(&[32], 5, 8, false),
+            Err(Error::InvalidBech32(Bech32Error::InvalidDataValue(32)))
+        );
+    }
+}
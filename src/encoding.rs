@@ -0,0 +1,358 @@
+#![allow(dead_code)]
+
+use crate::error::{DecodeError, Result};
+use std::io::{self, Read, Write};
+
+/// Largest CompactSize length prefix Bitcoin Core accepts (MAX_SIZE).
+pub const MAX_LENGTH: u64 = 0x02000000;
+
+/// Largest serialized block, and so the largest message worth decoding.
+pub const MAX_BYTES: u64 = 4_000_000;
+
+// Vectors grow as their items arrive instead of trusting the length prefix
+// for the whole allocation
+const MAX_PREALLOCATION: usize = 4096;
+
+/// Bounds for decoding untrusted data: `max_length` caps every length prefix
+/// and `max_bytes` the total input consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    pub max_length: u64,
+    pub max_bytes: u64,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        DecodeLimits {
+            max_length: MAX_LENGTH,
+            max_bytes: MAX_BYTES,
+        }
+    }
+}
+
+/// Bitcoin's binary serialization. `encode` returns the number of bytes
+/// written.
+pub trait Encodable {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize>;
+}
+
+pub trait Decodable: Sized {
+    fn decode<R: Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<Self>;
+}
+
+pub fn serialize<T: Encodable + ?Sized>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    value
+        .encode(&mut bytes)
+        .expect("writing to a Vec doesn't fail");
+    bytes
+}
+
+/// Decodes a value that must use all of `bytes`.
+pub fn deserialize<T: Decodable>(bytes: &[u8]) -> Result<T> {
+    deserialize_with_limits(bytes, &DecodeLimits::default())
+}
+
+pub fn deserialize_with_limits<T: Decodable>(bytes: &[u8], limits: &DecodeLimits) -> Result<T> {
+    let mut reader = bytes;
+    let value = decode_with_limits(&mut reader, limits)?;
+    if !reader.is_empty() {
+        return Err(DecodeError::TrailingBytes(reader.len()).into());
+    }
+    Ok(value)
+}
+
+/// Decodes a value from a stream, failing once it needs more than
+/// `limits.max_bytes`. At most one byte past the limit is read.
+pub fn decode_with_limits<T: Decodable, R: Read>(reader: R, limits: &DecodeLimits) -> Result<T> {
+    // one byte of slack tells a value over the limit from data that ends
+    // right at it
+    let mut reader = reader.take(limits.max_bytes.saturating_add(1));
+    let result = T::decode(&mut reader, limits);
+    if reader.limit() == 0 {
+        return Err(DecodeError::SizeLimitExceeded(limits.max_bytes).into());
+    }
+    result
+}
+
+macro_rules! impl_int {
+    ($($int:ty),*) => {
+        $(
+            impl Encodable for $int {
+                fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+                    writer.write_all(&self.to_le_bytes())?;
+                    Ok(std::mem::size_of::<$int>())
+                }
+            }
+
+            impl Decodable for $int {
+                fn decode<R: Read + ?Sized>(reader: &mut R, _: &DecodeLimits) -> Result<Self> {
+                    let mut bytes = [0u8; std::mem::size_of::<$int>()];
+                    reader.read_exact(&mut bytes)?;
+                    Ok(<$int>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_int!(u8, u16, u32, u64, i32, i64);
+
+/// Variable length integer used for lengths and counts: one byte below
+/// 0xfd, otherwise a 0xfd, 0xfe or 0xff marker followed by 2, 4 or 8 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CompactSize(pub u64);
+
+impl CompactSize {
+    pub fn encoded_size(&self) -> usize {
+        match self.0 {
+            0..=0xfc => 1,
+            0xfd..=0xffff => 3,
+            0x10000..=0xffffffff => 5,
+            _ => 9,
+        }
+    }
+}
+
+impl Encodable for CompactSize {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+        match self.0 {
+            0..=0xfc => (self.0 as u8).encode(writer),
+            0xfd..=0xffff => Ok(0xfdu8.encode(writer)? + (self.0 as u16).encode(writer)?),
+            0x10000..=0xffffffff => Ok(0xfeu8.encode(writer)? + (self.0 as u32).encode(writer)?),
+            _ => Ok(0xffu8.encode(writer)? + self.0.encode(writer)?),
+        }
+    }
+}
+
+impl Decodable for CompactSize {
+    /// Rejects values that a shorter form could encode, like Bitcoin Core.
+    fn decode<R: Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<Self> {
+        let (value, min) = match u8::decode(reader, limits)? {
+            0xfd => (u16::decode(reader, limits)? as u64, 0xfd),
+            0xfe => (u32::decode(reader, limits)? as u64, 0x10000),
+            0xff => (u64::decode(reader, limits)?, 0x100000000),
+            value => (value as u64, 0),
+        };
+        if value < min {
+            return Err(DecodeError::NonMinimalCompactSize(value).into());
+        }
+        Ok(CompactSize(value))
+    }
+}
+
+/// CompactSize length prefix checked against `limits.max_length`.
+pub fn decode_length<R: Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<usize> {
+    let CompactSize(length) = CompactSize::decode(reader, limits)?;
+    if length > limits.max_length {
+        return Err(DecodeError::OversizedLength {
+            length,
+            max: limits.max_length,
+        }
+        .into());
+    }
+    Ok(length as usize)
+}
+
+impl<T: Encodable> Encodable for [T] {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut written = CompactSize(self.len() as u64).encode(writer)?;
+        for item in self {
+            written += item.encode(writer)?;
+        }
+        Ok(written)
+    }
+}
+
+/// Length-prefixed vector.
+impl<T: Encodable> Encodable for Vec<T> {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+        self.as_slice().encode(writer)
+    }
+}
+
+impl<T: Decodable> Decodable for Vec<T> {
+    fn decode<R: Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<Self> {
+        let length = decode_length(reader, limits)?;
+        let item_size = std::mem::size_of::<T>().max(1);
+
+        let mut items = Vec::with_capacity(length.min(MAX_PREALLOCATION / item_size));
+        for _ in 0..length {
+            items.push(T::decode(reader, limits)?);
+        }
+        Ok(items)
+    }
+}
+
+/// Fixed size arrays have no length prefix.
+impl<T: Encodable, const N: usize> Encodable for [T; N] {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut written = 0;
+        for item in self {
+            written += item.encode(writer)?;
+        }
+        Ok(written)
+    }
+}
+
+impl<T: Decodable, const N: usize> Decodable for [T; N] {
+    fn decode<R: Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<Self> {
+        let mut items = Vec::with_capacity(N);
+        for _ in 0..N {
+            items.push(T::decode(reader, limits)?);
+        }
+        Ok(items
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("exactly N items were decoded")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(serialize(&0x12u8), [0x12]);
+        assert_eq!(serialize(&0x1234u16), [0x34, 0x12]);
+        assert_eq!(serialize(&0x12345678u32), [0x78, 0x56, 0x34, 0x12]);
+        assert_eq!(serialize(&-2i32), [0xfe, 0xff, 0xff, 0xff]);
+        assert_eq!(serialize(&1u64), [1, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert_eq!(
+            deserialize::<u32>(&[0x78, 0x56, 0x34, 0x12]),
+            Ok(0x12345678)
+        );
+        assert_eq!(deserialize::<i64>(&[0xff; 8]), Ok(-1));
+        assert_eq!(
+            deserialize::<u32>(&[1, 2, 3]),
+            Err(DecodeError::UnexpectedEnd.into())
+        );
+        assert_eq!(
+            deserialize::<u16>(&[1, 2, 3]),
+            Err(DecodeError::TrailingBytes(1).into())
+        );
+    }
+
+    #[test]
+    fn test_compact_size() {
+        let cases: [(u64, &str); 9] = [
+            (0, "00"),
+            (0xfc, "fc"),
+            (0xfd, "fdfd00"),
+            (0x1234, "fd3412"),
+            (0xffff, "fdffff"),
+            (0x10000, "fe00000100"),
+            (0xffffffff, "feffffffff"),
+            (0x100000000, "ff0000000001000000"),
+            (u64::MAX, "ffffffffffffffffff"),
+        ];
+
+        for (value, encoded) in cases {
+            let bytes = serialize(&CompactSize(value));
+            assert_eq!(hex::encode(&bytes), encoded);
+            assert_eq!(CompactSize(value).encoded_size(), bytes.len());
+            assert_eq!(deserialize::<CompactSize>(&bytes), Ok(CompactSize(value)));
+        }
+
+        for (encoded, value) in [
+            ("fdfc00", 0xfc),
+            ("feffff0000", 0xffff),
+            ("ffffffffff00000000", 0xffffffff),
+        ] {
+            assert_eq!(
+                deserialize::<CompactSize>(&hex::decode(encoded).unwrap()),
+                Err(DecodeError::NonMinimalCompactSize(value).into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_vectors_and_arrays() {
+        let items: Vec<u16> = vec![1, 2, 0xffff];
+        let bytes = serialize(&items);
+        assert_eq!(hex::encode(&bytes), "0301000200ffff");
+        assert_eq!(deserialize::<Vec<u16>>(&bytes), Ok(items));
+
+        let nested = vec![vec![0xabu8; 3], vec![], vec![1u8; 300]];
+        let bytes = serialize(&nested);
+        assert_eq!(bytes.len(), 1 + 4 + 1 + 3 + 300);
+        assert_eq!(deserialize::<Vec<Vec<u8>>>(&bytes), Ok(nested));
+
+        let array = [0x11u8, 0x22, 0x33, 0x44];
+        assert_eq!(serialize(&array), array);
+        assert_eq!(deserialize::<[u8; 4]>(&array), Ok(array));
+        assert_eq!(serialize(&[1u16, 2]), [1, 0, 2, 0]);
+    }
+
+    #[test]
+    fn test_limits() {
+        // a length prefix of 2^32 items with nothing after it
+        let bytes = hex::decode("ff0000000001000000").unwrap();
+        assert_eq!(
+            deserialize::<Vec<u8>>(&bytes),
+            Err(DecodeError::OversizedLength {
+                length: 0x100000000,
+                max: MAX_LENGTH
+            }
+            .into())
+        );
+
+        let limits = DecodeLimits {
+            max_length: 10,
+            max_bytes: MAX_BYTES,
+        };
+        assert!(deserialize_with_limits::<Vec<u8>>(&serialize(&vec![0u8; 10]), &limits).is_ok());
+        assert_eq!(
+            deserialize_with_limits::<Vec<u8>>(&serialize(&vec![0u8; 11]), &limits),
+            Err(DecodeError::OversizedLength {
+                length: 11,
+                max: 10
+            }
+            .into())
+        );
+
+        // a length below max_length whose items don't fit in max_bytes
+        let limits = DecodeLimits {
+            max_length: MAX_LENGTH,
+            max_bytes: 100,
+        };
+        let bytes = serialize(&vec![0u32; 30]);
+        assert_eq!(
+            decode_with_limits::<Vec<u32>, _>(&bytes[..], &limits),
+            Err(DecodeError::SizeLimitExceeded(100).into())
+        );
+        assert_eq!(
+            decode_with_limits::<Vec<u32>, _>(&bytes[..100], &DecodeLimits::default()),
+            Err(DecodeError::UnexpectedEnd.into())
+        );
+        // a value of exactly max_bytes leaves the rest of the stream unread
+        let mut reader = &bytes[..];
+        let limits = DecodeLimits {
+            max_length: MAX_LENGTH,
+            max_bytes: 97,
+        };
+        assert_eq!(
+            decode_with_limits::<[u8; 97], _>(&mut reader, &limits),
+            Ok(bytes[..97].try_into().unwrap())
+        );
+        assert_eq!(reader, &bytes[97..]);
+        let limits = DecodeLimits {
+            max_length: MAX_LENGTH,
+            max_bytes: 100,
+        };
+
+        // truncated data exactly max_bytes long
+        assert_eq!(
+            decode_with_limits::<Vec<u32>, _>(&bytes[..100], &limits),
+            Err(DecodeError::UnexpectedEnd.into())
+        );
+
+        // truncated data declaring a huge vector fails without allocating it
+        let bytes = hex::decode("fe00000001").unwrap();
+        assert_eq!(
+            deserialize::<Vec<u64>>(&bytes),
+            Err(DecodeError::UnexpectedEnd.into())
+        );
+    }
+}
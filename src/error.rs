@@ -0,0 +1,294 @@
+use crate::address::Network;
+use num_bigint::BigUint;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum Error {
+    #[error("Finite fields elements have different order lhs: {lhs}, rhs: {rhs}")]
+    DifferentFieldOrder { lhs: BigUint, rhs: BigUint },
+
+    #[error("field element does not fit in 32 bytes or is not smaller than the prime")]
+    FieldElementOutOfRange,
+
+    #[error("({x:x},{y:x}) point is not in the curve")]
+    PointNotOnCurve { x: BigUint, y: BigUint },
+
+    #[error("the points belong to different curves")]
+    DifferentCurves,
+
+    #[error("invalid SEC prefix {0:#04x}")]
+    InvalidSecPrefix(u8),
+
+    #[error("invalid SEC length {len} for prefix {prefix:#04x}")]
+    InvalidSecLength { prefix: u8, len: usize },
+
+    #[error("there is no point in the curve with x = {0:x}")]
+    InvalidSecX(BigUint),
+
+    #[error("the point at infinity is not a valid public key")]
+    PointAtInfinity,
+
+    #[error("it was not posible to generate the random point")]
+    InvalidNonce,
+
+    #[error("invalid recovery id {0}, expected 0..=3")]
+    InvalidRecoveryId(u8),
+
+    #[error("no public key can be recovered from the signature")]
+    RecoveryFailed,
+
+    #[error("compact signatures are 65 bytes long, found {0}")]
+    InvalidCompactLength(usize),
+
+    #[error("invalid compact signature header {0}, expected 27..=34")]
+    InvalidCompactHeader(u8),
+
+    #[error("expected {expected} bytes, found {found}")]
+    InvalidLength { expected: usize, found: usize },
+
+    #[error("secret keys must be in the range [1, n - 1]")]
+    InvalidSecretKey,
+
+    #[error("the OS random number generator failed: {0}")]
+    RandomSource(String),
+
+    #[error("the taproot tweak is not smaller than the group order")]
+    InvalidTweak,
+
+    #[error("the produced signature doesn't verify")]
+    SignatureNotVerified,
+
+    #[error("the signature values don't fit their 32-byte range")]
+    SignatureOutOfRange,
+
+    #[error("the signatures at positions {0:?} don't verify")]
+    InvalidSignatures(Vec<usize>),
+
+    #[error("invalid DER signature: {0}")]
+    InvalidDer(DerError),
+
+    #[error("invalid hex string: {0}")]
+    InvalidHex(String),
+
+    #[error("invalid base58 string: {0}")]
+    InvalidBase58(Base58Error),
+
+    #[error("invalid bech32 string: {0}")]
+    InvalidBech32(Bech32Error),
+
+    #[error("invalid address: {0}")]
+    InvalidAddress(AddressError),
+
+    #[error("invalid WIF private key: {0}")]
+    InvalidWif(WifError),
+
+    #[error("decoding failed: {0}")]
+    Decode(DecodeError),
+
+    #[error("input index {index} is out of range for {inputs} inputs")]
+    InputIndexOutOfRange { index: usize, inputs: usize },
+}
+
+/// The DER (BIP66) rule a signature encoding breaks. `R` and `S` name the
+/// integer the rule applies to.
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DerError {
+    #[error("length {0} is outside the 8..=72 range")]
+    InvalidLength(usize),
+
+    #[error("expected the sequence tag 0x30, found {0:#04x}")]
+    NotASequence(u8),
+
+    #[error("the sequence length byte says {declared} but {actual} bytes follow")]
+    WrongSequenceLength { declared: usize, actual: usize },
+
+    #[error("{0} length byte doesn't match the signature length")]
+    WrongIntegerLength(&'static str),
+
+    #[error("{0} is not an integer")]
+    NotAnInteger(&'static str),
+
+    #[error("{0} has zero length")]
+    ZeroLengthInteger(&'static str),
+
+    #[error("{0} is negative")]
+    NegativeInteger(&'static str),
+
+    #[error("{0} has excess zero padding")]
+    ExcessPadding(&'static str),
+
+    #[error("the signature ends unexpectedly")]
+    UnexpectedEnd,
+}
+
+/// Why a base58 or base58check string was rejected.
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Base58Error {
+    #[error("invalid character {character:?} at position {position}")]
+    InvalidCharacter { character: char, position: usize },
+
+    #[error("base58check data must be at least 4 bytes long, found {0}")]
+    TooShort(usize),
+
+    #[error("checksum {found:08x} doesn't match the expected {expected:08x}")]
+    InvalidChecksum { expected: u32, found: u32 },
+}
+
+/// Why a bech32/bech32m string or segwit address was rejected. Positions
+/// are character indexes in the string.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum Bech32Error {
+    #[error("the string mixes upper and lower case characters")]
+    MixedCase,
+
+    #[error("missing the '1' separator")]
+    MissingSeparator,
+
+    #[error("the human readable part must be 1 to 83 characters long")]
+    InvalidHrp,
+
+    #[error("length {0} is longer than 90 characters")]
+    InvalidLength(usize),
+
+    #[error("the data part is shorter than the 6 checksum characters")]
+    ChecksumTooShort,
+
+    #[error("invalid character {character:?} at position {position}")]
+    InvalidCharacter { character: char, position: usize },
+
+    #[error("invalid checksum, likely errors at positions {0:?}")]
+    InvalidChecksum(Vec<usize>),
+
+    #[error("data value {0} doesn't fit in 5 bits")]
+    InvalidDataValue(u8),
+
+    #[error("invalid padding in the 8 to 5 bits conversion")]
+    InvalidPadding,
+
+    #[error("expected the human readable part {expected:?}, found {found:?}")]
+    WrongHrp { expected: String, found: String },
+
+    #[error("the data part doesn't have a witness version")]
+    MissingWitnessVersion,
+
+    #[error("invalid witness version {0}")]
+    InvalidWitnessVersion(u8),
+
+    #[error("invalid witness program length {length} for version {version}")]
+    InvalidProgramLength { version: u8, length: usize },
+
+    #[error("witness version {0} is encoded with the wrong checksum variant")]
+    WrongVariant(u8),
+}
+
+/// Why an address string was rejected, beyond its base58 or bech32
+/// encoding.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum AddressError {
+    #[error("base58 addresses have 21 bytes, found {0}")]
+    InvalidLength(usize),
+
+    #[error("unknown base58 version byte {0:#04x}")]
+    UnknownPrefix(u8),
+
+    #[error("unknown human readable part in {0:?}")]
+    UnknownHrp(String),
+
+    #[error("the address is not valid for {0:?}")]
+    WrongNetwork(Network),
+}
+
+/// Why a Wallet Import Format string was rejected, beyond its base58check
+/// encoding.
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum WifError {
+    #[error("WIF keys have 33 or 34 bytes, found {0}")]
+    InvalidLength(usize),
+
+    #[error("unknown version byte {0:#04x}")]
+    UnknownPrefix(u8),
+
+    #[error("invalid compression flag {0:#04x}, expected 0x01")]
+    InvalidCompressionFlag(u8),
+}
+
+/// Why binary decoding stopped.
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DecodeError {
+    #[error("the data ends unexpectedly")]
+    UnexpectedEnd,
+
+    #[error("I/O error: {0:?}")]
+    Io(std::io::ErrorKind),
+
+    #[error("CompactSize {0} is not minimally encoded")]
+    NonMinimalCompactSize(u64),
+
+    #[error("length {length} is larger than the limit of {max}")]
+    OversizedLength { length: u64, max: u64 },
+
+    #[error("the data is larger than the limit of {0} bytes")]
+    SizeLimitExceeded(u64),
+
+    #[error("{0} bytes left after the decoded value")]
+    TrailingBytes(usize),
+
+    #[error("unsupported segwit flag {0:#04x}")]
+    UnsupportedSegwitFlag(u8),
+
+    #[error("segwit transaction without any witness")]
+    SuperfluousWitness,
+}
+
+impl From<DerError> for Error {
+    fn from(error: DerError) -> Self {
+        Error::InvalidDer(error)
+    }
+}
+
+impl From<Base58Error> for Error {
+    fn from(error: Base58Error) -> Self {
+        Error::InvalidBase58(error)
+    }
+}
+
+impl From<Bech32Error> for Error {
+    fn from(error: Bech32Error) -> Self {
+        Error::InvalidBech32(error)
+    }
+}
+
+impl From<AddressError> for Error {
+    fn from(error: AddressError) -> Self {
+        Error::InvalidAddress(error)
+    }
+}
+
+impl From<WifError> for Error {
+    fn from(error: WifError) -> Self {
+        Error::InvalidWif(error)
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(error: DecodeError) -> Self {
+        Error::Decode(error)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::UnexpectedEof => DecodeError::UnexpectedEnd.into(),
+            kind => DecodeError::Io(kind).into(),
+        }
+    }
+}
+
+impl From<hex::FromHexError> for Error {
+    fn from(error: hex::FromHexError) -> Self {
+        Error::InvalidHex(error.to_string())
+    }
+}
@@ -1,9 +1,35 @@
 #![allow(dead_code)]
 
-use num::{Integer, One};
+use crate::error::{Error, Result};
+use num::{Integer, One, Zero};
 use num_bigint::{BigInt, BigUint, ToBigInt};
+use std::fmt::Debug;
 use std::ops::{Add, Div, Mul, Sub};
 
+/// Operations a field element needs to provide so elliptic curve points can be
+/// built on top of it.
+pub trait Field:
+    Clone
+    + PartialEq
+    + Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+{
+    fn pow(self, exp: &BigInt) -> Self;
+
+    fn scale(self, scalar: BigUint) -> Self;
+
+    fn to_biguint(&self) -> BigUint;
+
+    /// Additive identity of the field `self` belongs to.
+    fn zero_element(&self) -> Self;
+
+    /// Multiplicative identity of the field `self` belongs to.
+    fn one_element(&self) -> Self;
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct FiniteField {
     pub number: BigUint,
@@ -18,13 +44,57 @@ impl FiniteField {
         FiniteField { number, prime }
     }
 
-    fn check_equal_order_and_panic(self: &Self, rhs: &FiniteField) {
+    fn check_equal_order(&self, rhs: &FiniteField) -> Result<()> {
         if self.prime != rhs.prime {
-            panic!(
-                "Finite fields elements have different order lhs: {}, rhs: {}",
-                self.prime, rhs.prime
-            )
+            return Err(Error::DifferentFieldOrder {
+                lhs: self.prime.clone(),
+                rhs: rhs.prime.clone(),
+            });
         }
+        Ok(())
+    }
+
+    pub fn try_add(self, rhs: FiniteField) -> Result<FiniteField> {
+        self.check_equal_order(&rhs)?;
+
+        Ok(FiniteField {
+            number: (self.number + rhs.number) % self.prime.clone(),
+            prime: self.prime,
+        })
+    }
+
+    pub fn try_sub(self, rhs: FiniteField) -> Result<FiniteField> {
+        self.check_equal_order(&rhs)?;
+
+        if self.number >= rhs.number {
+            Ok(FiniteField {
+                number: (self.number - rhs.number) % self.prime.clone(),
+                prime: self.prime,
+            })
+        } else {
+            Ok(FiniteField {
+                number: (self.number + self.prime.clone() - rhs.number) % self.prime.clone(),
+                prime: self.prime,
+            })
+        }
+    }
+
+    pub fn try_mul(self, rhs: FiniteField) -> Result<FiniteField> {
+        self.check_equal_order(&rhs)?;
+
+        Ok(FiniteField {
+            number: (self.number * rhs.number) % self.prime.clone(),
+            prime: self.prime,
+        })
+    }
+
+    pub fn try_div(self, rhs: FiniteField) -> Result<FiniteField> {
+        self.check_equal_order(&rhs)?;
+
+        let exp = (self.prime.clone() - BigUint::from(2u32))
+            .to_bigint()
+            .unwrap();
+        self.try_mul(rhs.pow(&exp))
     }
 
     pub fn pow(self, exp: &BigInt) -> FiniteField {
@@ -45,6 +115,125 @@ impl FiniteField {
             prime: self.prime,
         }
     }
+
+    /// Legendre symbol (a/p) computed with Euler's criterion: returns 0 when
+    /// the element is zero, 1 when it is a non-zero square and -1 otherwise.
+    pub fn legendre_symbol(&self) -> i8 {
+        if self.number.is_zero() {
+            return 0;
+        }
+
+        let exp = (self.prime.clone() - BigUint::one()) >> 1;
+        if self.number.modpow(&exp, &self.prime).is_one() {
+            1
+        } else {
+            -1
+        }
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.legendre_symbol() >= 0
+    }
+
+    /// Returns one of the square roots of the element, or `None` if it is not
+    /// a quadratic residue. The other root is `prime - root`.
+    pub fn sqrt(&self) -> Option<FiniteField> {
+        // in F_2 both elements are their own root, and there is no
+        // non-residue for Tonelli-Shanks to find
+        if self.number.is_zero() || self.prime == BigUint::from(2u32) {
+            return Some(self.clone());
+        }
+
+        if !self.is_square() {
+            return None;
+        }
+
+        let p = &self.prime;
+        let number = if p % 4u32 == BigUint::from(3u32) {
+            // sqrt(a) = a^((p + 1) / 4) when p = 3 mod 4
+            self.number.modpow(&((p + BigUint::one()) >> 2), p)
+        } else {
+            self.tonelli_shanks()
+        };
+
+        Some(FiniteField {
+            number,
+            prime: self.prime.clone(),
+        })
+    }
+
+    fn tonelli_shanks(&self) -> BigUint {
+        let p = &self.prime;
+        let one = BigUint::one();
+        let p_minus_1 = p - &one;
+
+        // p - 1 = q * 2^s with q odd
+        let s = p_minus_1.trailing_zeros().unwrap_or(0);
+        let q = &p_minus_1 >> s;
+
+        // any quadratic non-residue works as z
+        let mut z = BigUint::from(2u32);
+        while (FiniteField {
+            number: z.clone(),
+            prime: p.clone(),
+        })
+        .legendre_symbol()
+            != -1
+        {
+            z += 1u32;
+        }
+
+        let mut m = s;
+        let mut c = z.modpow(&q, p);
+        let mut t = self.number.modpow(&q, p);
+        let mut r = self.number.modpow(&((&q + &one) >> 1), p);
+
+        while !t.is_one() {
+            // least i such that t^(2^i) = 1
+            let mut i = 0;
+            let mut t_pow = t.clone();
+            while !t_pow.is_one() {
+                t_pow = (&t_pow * &t_pow) % p;
+                i += 1;
+            }
+
+            let b = c.modpow(&(BigUint::one() << (m - i - 1)), p);
+            m = i;
+            c = (&b * &b) % p;
+            t = (t * &c) % p;
+            r = (r * b) % p;
+        }
+
+        r
+    }
+}
+
+impl Field for FiniteField {
+    fn pow(self, exp: &BigInt) -> Self {
+        FiniteField::pow(self, exp)
+    }
+
+    fn scale(self, scalar: BigUint) -> Self {
+        FiniteField::scale(self, scalar)
+    }
+
+    fn to_biguint(&self) -> BigUint {
+        self.number.clone()
+    }
+
+    fn zero_element(&self) -> Self {
+        FiniteField {
+            number: BigUint::zero(),
+            prime: self.prime.clone(),
+        }
+    }
+
+    fn one_element(&self) -> Self {
+        FiniteField {
+            number: BigUint::one(),
+            prime: self.prime.clone(),
+        }
+    }
 }
 
 impl From<(u32, u32)> for FiniteField {
@@ -59,13 +248,8 @@ impl From<(u32, u32)> for FiniteField {
 impl Add for FiniteField {
     type Output = FiniteField;
 
-    fn add(self, _rhs: FiniteField) -> FiniteField {
-        self.check_equal_order_and_panic(&_rhs);
-
-        FiniteField {
-            number: (self.number + _rhs.number) % self.prime.clone(),
-            prime: self.prime,
-        }
+    fn add(self, rhs: FiniteField) -> FiniteField {
+        self.try_add(rhs).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
@@ -73,19 +257,7 @@ impl Sub for FiniteField {
     type Output = FiniteField;
 
     fn sub(self, rhs: FiniteField) -> FiniteField {
-        self.check_equal_order_and_panic(&rhs);
-
-        if self.number >= rhs.number {
-            FiniteField {
-                number: (self.number - rhs.number) % self.prime.clone(),
-                prime: self.prime,
-            }
-        } else {
-            FiniteField {
-                number: (self.number + self.prime.clone() - rhs.number) % self.prime.clone(),
-                prime: self.prime,
-            }
-        }
+        self.try_sub(rhs).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
@@ -93,12 +265,7 @@ impl Mul for FiniteField {
     type Output = FiniteField;
 
     fn mul(self, rhs: FiniteField) -> FiniteField {
-        self.check_equal_order_and_panic(&rhs);
-
-        FiniteField {
-            number: (self.number * rhs.number) % self.prime.clone(),
-            prime: self.prime,
-        }
+        self.try_mul(rhs).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
@@ -106,13 +273,12 @@ impl Div for FiniteField {
     type Output = FiniteField;
 
     fn div(self, rhs: FiniteField) -> FiniteField {
-        self.check_equal_order_and_panic(&rhs);
-
-        self.clone() * rhs.pow(&(self.prime - BigUint::from(2u32)).to_bigint().unwrap())
+        self.try_div(rhs).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::identity_op)]
 mod tests {
     use super::*;
 
@@ -159,6 +325,26 @@ mod tests {
         let _c = a + b;
     }
 
+    #[test]
+    fn test_try_operations_different_orders() {
+        let a = FiniteField::from((6, 12));
+        let b = FiniteField::from((5, 11));
+        let error = Error::DifferentFieldOrder {
+            lhs: BigUint::from(12u32),
+            rhs: BigUint::from(11u32),
+        };
+
+        assert_eq!(a.clone().try_add(b.clone()), Err(error.clone()));
+        assert_eq!(a.clone().try_sub(b.clone()), Err(error.clone()));
+        assert_eq!(a.clone().try_mul(b.clone()), Err(error.clone()));
+        assert_eq!(a.try_div(b), Err(error));
+
+        let a = FiniteField::from((3, 31));
+        let b = FiniteField::from((24, 31));
+
+        assert_eq!(a.try_div(b), Ok(FiniteField::from((4, 31))));
+    }
+
     #[test]
     fn test_substract() {
         let a = FiniteField::from((6, 11));
@@ -287,6 +473,96 @@ mod tests {
         assert_eq!(a.pow(&BigInt::from(-4)) * b, c);
     }
 
+    #[test]
+    fn test_sqrt_f223() {
+        let prime = 223;
+
+        let squares = (0..prime)
+            .map(|i| FiniteField::from((i, prime)))
+            .filter(|a| a.is_square())
+            .count();
+
+        assert_eq!(squares, 112);
+
+        for i in 0..prime {
+            let a = FiniteField::from((i, prime));
+            match a.sqrt() {
+                Some(root) => assert_eq!(root.clone() * root, a),
+                None => assert_eq!(a.legendre_symbol(), -1),
+            }
+        }
+
+        // recover y from x on the book's curve y^2 = x^3 + 7
+        let x = FiniteField::from((192, prime));
+        let y = FiniteField::from((105, prime));
+        let y_square = x.pow(&BigInt::from(3u32)) + FiniteField::from((7, prime));
+        let root = y_square.sqrt().unwrap();
+
+        assert!(root == y || root == FiniteField::from((prime - 105, prime)));
+
+        let x = FiniteField::from((47, prime));
+        let y_square = x.pow(&BigInt::from(3u32)) + FiniteField::from((7, prime));
+        let root = y_square.sqrt().unwrap();
+
+        assert!(root == FiniteField::from((71, prime)) || root == FiniteField::from((152, prime)));
+    }
+
+    #[test]
+    fn test_sqrt_tonelli_shanks() {
+        // primes congruent to 1 mod 4 go through Tonelli-Shanks
+        for prime in [13, 17, 41, 97, 257] {
+            let mut squares = 0;
+            for i in 0..prime {
+                let a = FiniteField::from((i, prime));
+                if let Some(root) = a.sqrt() {
+                    assert_eq!(root.clone() * root, a);
+                    squares += 1;
+                } else {
+                    assert!(!a.is_square());
+                }
+            }
+            assert_eq!(squares, (prime - 1) / 2 + 1);
+        }
+
+        for i in 0..2 {
+            let a = FiniteField::from((i, 2));
+            assert_eq!(a.sqrt(), Some(a));
+        }
+    }
+
+    #[test]
+    fn test_legendre_symbol() {
+        assert_eq!(FiniteField::from((0, 223)).legendre_symbol(), 0);
+        assert_eq!(FiniteField::from((1, 223)).legendre_symbol(), 1);
+        assert_eq!(FiniteField::from((4, 17)).legendre_symbol(), 1);
+        assert_eq!(FiniteField::from((3, 17)).legendre_symbol(), -1);
+        assert_eq!(FiniteField::from((222, 223)).legendre_symbol(), -1);
+    }
+
+    #[test]
+    fn test_sqrt_secp256k1() {
+        let prime = hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F")
+            .unwrap();
+        let gx = hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+            .unwrap();
+        let gy = hex::decode("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")
+            .unwrap();
+
+        let x = FiniteField::from_bytes_be(&gx, &prime);
+        let y = FiniteField::from_bytes_be(&gy, &prime);
+        let b = FiniteField::from_bytes_be(&[7], &prime);
+
+        let root = (x.pow(&BigInt::from(3u32)) + b).sqrt().unwrap();
+        let neg_root = FiniteField::from_bytes_be(&[0], &prime) - root.clone();
+
+        assert!(root == y || neg_root == y);
+
+        // -1 is not a square when p = 3 mod 4
+        let minus_one =
+            FiniteField::from_bytes_be(&[0], &prime) - FiniteField::from_bytes_be(&[1], &prime);
+        assert_eq!(minus_one.sqrt(), None);
+    }
+
     #[test]
     fn test_from_bytes_be() {
         let a = FiniteField::from_bytes_be(&[0x01, 0x02], &[0x01, 0x12]);
@@ -0,0 +1,178 @@
+#![allow(dead_code)]
+
+use crate::point::Point;
+use crate::secp256k1::Secp256k1Point;
+use crate::secp256k1_field::Secp256k1Field;
+use num::{Signed, Zero};
+use num_bigint::{BigInt, BigUint, Sign};
+
+// secp256k1 has the endomorphism phi(x, y) = (beta * x, y) = lambda * (x, y),
+// where beta and lambda are non trivial cube roots of unity mod p and mod n.
+// Gallant, Lambert and Vanstone use it to split a 256-bit scalar multiplication
+// into two ~128-bit ones sharing the same doublings.
+const BETA: &str = "7ae96a2b657c07106e64479eac3434e99cf0497512f58995c1396c28719501ee";
+const LAMBDA: &str = "5363ad4cc05c30e0a5261c028812645a122e22ea20816678df02967c1b23bd72";
+
+// Short basis {(a1, b1), (a2, b2)} of the lattice of (x, y) with
+// x + y * lambda = 0 mod n, found with the extended Euclidean algorithm.
+const A1: &str = "3086d221a7d46bcde86c90e49284eb15";
+const MINUS_B1: &str = "e4437ed6010e88286f547fa90abfe4c3";
+const A2: &str = "114ca50f7a8e2f3f657c1108d9d44cfd8";
+const B2: &str = "3086d221a7d46bcde86c90e49284eb15";
+
+fn from_hex(value: &str) -> BigInt {
+    BigInt::parse_bytes(value.as_bytes(), 16).unwrap()
+}
+
+pub fn lambda() -> BigUint {
+    from_hex(LAMBDA).to_biguint().unwrap()
+}
+
+pub fn beta() -> Secp256k1Field {
+    Secp256k1Field::from_bytes_be(&hex::decode(BETA).unwrap())
+}
+
+// round(a / b) for non-negative a and positive b
+fn div_round(a: &BigInt, b: &BigInt) -> BigInt {
+    (a * 2 + b) / (b * 2)
+}
+
+/// Splits k into (k1, k2) with k = k1 + k2 * lambda (mod n) and both halves
+/// around 128 bits long. The halves can be negative.
+pub fn decompose_scalar(k: &BigUint) -> (BigInt, BigInt) {
+    let n = BigInt::from(Secp256k1Point::n());
+    let k = BigInt::from(k % Secp256k1Point::n());
+
+    let (a1, minus_b1) = (from_hex(A1), from_hex(MINUS_B1));
+    let (a2, b2) = (from_hex(A2), from_hex(B2));
+
+    let c1 = div_round(&(&b2 * &k), &n);
+    let c2 = div_round(&(&minus_b1 * &k), &n);
+
+    let k1 = k - &c1 * a1 - &c2 * a2;
+    let k2 = c1 * minus_b1 - c2 * b2;
+    (k1, k2)
+}
+
+// (|k| * P) folded into a multi_scale term, negating P for negative k
+fn signed_term(point: Secp256k1Point, k: BigInt) -> (Secp256k1Point, BigUint) {
+    let point = if k.sign() == Sign::Minus {
+        -point
+    } else {
+        point
+    };
+    (point, k.abs().to_biguint().unwrap())
+}
+
+impl Secp256k1Point {
+    /// phi(P) = (beta * x, y), which equals lambda * P.
+    pub fn endomorphism(&self) -> Secp256k1Point {
+        match self {
+            Point::Coor { a, b, x, y } => Point::Coor {
+                a: *a,
+                b: *b,
+                x: beta() * *x,
+                y: *y,
+            },
+            Point::Zero => Point::Zero,
+        }
+    }
+
+    /// k * P computed as k1 * P + k2 * phi(P) with half-length scalars.
+    pub fn scale_glv(&self, k: &BigUint) -> Secp256k1Point {
+        Self::multi_scale_glv(&[(self.clone(), k.clone())])
+    }
+
+    /// Same as `Point::multi_scale`, splitting every term with the
+    /// endomorphism first so all the scalars are ~128 bits long.
+    pub fn multi_scale_glv(terms: &[(Secp256k1Point, BigUint)]) -> Secp256k1Point {
+        let terms = terms
+            .iter()
+            .filter(|(_, k)| !k.is_zero())
+            .flat_map(|(point, k)| {
+                let (k1, k2) = decompose_scalar(k);
+                [
+                    signed_term(point.clone(), k1),
+                    signed_term(point.endomorphism(), k2),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        Point::multi_scale(&terms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::Integer;
+
+    fn scalars() -> Vec<BigUint> {
+        let n = Secp256k1Point::n();
+        [
+            "00",
+            "01",
+            "02",
+            "3039",
+            "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721",
+            "7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0",
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "e4437ed6010e88286f547fa90abfe4c3",
+            "5363ad4cc05c30e0a5261c028812645a122e22ea20816678df02967c1b23bd72",
+        ]
+        .iter()
+        .map(|k| BigUint::parse_bytes(k.as_bytes(), 16).unwrap())
+        .chain([n.clone() - 1u32, n.clone() - 2u32, n])
+        .collect()
+    }
+
+    #[test]
+    fn test_constants() {
+        let g = Secp256k1Point::generator();
+
+        assert_eq!(g.endomorphism(), g.clone().scale(lambda()));
+        assert_eq!(beta() * beta() * beta(), Secp256k1Field::one());
+        assert_eq!(
+            lambda().modpow(&BigUint::from(3u32), &Secp256k1Point::n()),
+            BigUint::from(1u32)
+        );
+    }
+
+    #[test]
+    fn test_decompose_scalar() {
+        let n = BigInt::from(Secp256k1Point::n());
+        let lambda = BigInt::from(lambda());
+        let bound = BigInt::from(1u32) << 129;
+
+        for k in scalars() {
+            let (k1, k2) = decompose_scalar(&k);
+
+            let recomposed = (&k1 + &k2 * &lambda).mod_floor(&n);
+            assert_eq!(recomposed, BigInt::from(k) % &n);
+            assert!(k1.abs() < bound && k2.abs() < bound);
+        }
+    }
+
+    #[test]
+    fn test_scale_glv_matches_scale() {
+        let points = [
+            Secp256k1Point::generator(),
+            Secp256k1Point::generator().scale(BigUint::from(987654321u32)),
+        ];
+
+        for p in points.iter() {
+            for k in scalars() {
+                assert_eq!(p.scale_glv(&k), p.clone().scale(k.clone()));
+            }
+        }
+
+        assert_eq!(
+            Secp256k1Point::multi_scale_glv(&[
+                (points[0].clone(), scalars()[4].clone()),
+                (points[1].clone(), scalars()[5].clone()),
+            ]),
+            points[0].clone().scale(scalars()[4].clone())
+                + points[1].clone().scale(scalars()[5].clone())
+        );
+    }
+}
@@ -1,31 +1,419 @@
 #![allow(dead_code)]
 
-use hex;
-use sha256::digest;
+use crate::encoding::{serialize, CompactSize, Decodable, DecodeLimits, Encodable};
+use crate::error::{Error, Result};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io;
+use std::str::FromStr;
 
-pub fn sha256_double(z: &str) -> Vec<u8> {
-    let z = hex::decode(digest(z)).unwrap();
-    let z: &[u8] = &z;
-    hex::decode(digest(z)).unwrap()
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
 }
 
-pub fn sha256(z: &str) -> Vec<u8> {
-    let z = hex::decode(digest(z)).unwrap();
-    z
+/// SHA256(SHA256(data)), used for transaction ids, block hashes and
+/// checksums.
+pub fn sha256_double(data: &[u8]) -> [u8; 32] {
+    sha256(&sha256(data))
+}
+
+pub fn ripemd160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(data).into()
+}
+
+/// RIPEMD160(SHA256(data)), the hash of public keys and scripts in
+/// addresses.
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    ripemd160(&sha256(data))
+}
+
+/// SHA256(SHA256(tag) || SHA256(tag) || msg), the domain separated hash of
+/// BIP340 and taproot.
+pub fn tagged_hash(tag: &str, msg: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256Hasher::tagged(tag);
+    hasher.update(msg);
+    hasher.finalize()
+}
+
+/// Incremental SHA256, so large payloads can be hashed as they are
+/// serialized. It is also an `io::Write`.
+#[derive(Clone, Default)]
+pub struct Sha256Hasher {
+    engine: Sha256,
+}
+
+impl Sha256Hasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hasher with the prefix of `tagged_hash` already fed in.
+    pub fn tagged(tag: &str) -> Self {
+        let tag = sha256(tag.as_bytes());
+
+        let mut hasher = Self::new();
+        hasher.update(&tag);
+        hasher.update(&tag);
+        hasher
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.engine.update(data);
+    }
+
+    pub fn finalize(self) -> [u8; 32] {
+        self.engine.finalize().into()
+    }
+}
+
+/// Incremental `sha256_double`.
+#[derive(Clone, Default)]
+pub struct Sha256dHasher {
+    inner: Sha256Hasher,
+}
+
+impl Sha256dHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> [u8; 32] {
+        sha256(&self.inner.finalize())
+    }
+}
+
+/// Incremental `hash160`.
+#[derive(Clone, Default)]
+pub struct Hash160Hasher {
+    inner: Sha256Hasher,
+}
+
+impl Hash160Hasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> [u8; 20] {
+        ripemd160(&self.inner.finalize())
+    }
+}
+
+macro_rules! impl_write {
+    ($hasher:ty) => {
+        impl io::Write for $hasher {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.update(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_write!(Sha256Hasher);
+impl_write!(Sha256dHasher);
+impl_write!(Hash160Hasher);
+
+/// Declares a fixed size hash newtype. Bitcoin shows double SHA256 based
+/// hashes (txids, block hashes) with their bytes reversed, which is what
+/// `reversed` selects for `Display` and `FromStr`.
+macro_rules! hash_newtype {
+    ($(#[$doc:meta])* $name:ident, $len:expr, reversed = $reversed:expr) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name([u8; $len]);
+
+        impl $name {
+            pub const LEN: usize = $len;
+
+            pub const fn from_byte_array(bytes: [u8; $len]) -> Self {
+                $name(bytes)
+            }
+
+            pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+                let bytes = bytes.try_into().map_err(|_| Error::InvalidLength {
+                    expected: $len,
+                    found: bytes.len(),
+                })?;
+                Ok($name(bytes))
+            }
+
+            pub fn to_byte_array(self) -> [u8; $len] {
+                self.0
+            }
+
+            pub fn as_byte_array(&self) -> &[u8; $len] {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let mut bytes = self.0;
+                if $reversed {
+                    bytes.reverse();
+                }
+                write!(f, "{}", hex::encode(bytes))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self)
+            }
+        }
+
+        // Serialized in internal byte order, not reversed
+        impl Encodable for $name {
+            fn encode<W: io::Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+                self.0.encode(writer)
+            }
+        }
+
+        impl Decodable for $name {
+            fn decode<R: io::Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<Self> {
+                Ok($name(<[u8; $len]>::decode(reader, limits)?))
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(s: &str) -> Result<Self> {
+                let mut bytes = $name::from_slice(&hex::decode(s)?)?.0;
+                if $reversed {
+                    bytes.reverse();
+                }
+                Ok($name(bytes))
+            }
+        }
+    };
+}
+
+hash_newtype!(
+    /// Generic double SHA256 digest.
+    Sha256d, 32, reversed = true
+);
+hash_newtype!(
+    /// Transaction id, the double SHA256 of the transaction without witness.
+    Txid, 32, reversed = true
+);
+hash_newtype!(
+    /// BIP141 witness transaction id.
+    Wtxid, 32, reversed = true
+);
+hash_newtype!(
+    /// Double SHA256 of a block header.
+    BlockHash, 32, reversed = true
+);
+hash_newtype!(
+    /// RIPEMD160(SHA256(data)) of a public key or a script.
+    Hash160, 20, reversed = false
+);
+hash_newtype!(
+    /// BIP341 hash of a tapscript leaf.
+    TapLeafHash, 32, reversed = false
+);
+
+impl Sha256d {
+    pub fn hash(data: &[u8]) -> Self {
+        Sha256d(sha256_double(data))
+    }
+}
+
+impl Txid {
+    pub fn hash(data: &[u8]) -> Self {
+        Txid(sha256_double(data))
+    }
+}
+
+impl Wtxid {
+    pub fn hash(data: &[u8]) -> Self {
+        Wtxid(sha256_double(data))
+    }
+}
+
+impl BlockHash {
+    pub fn hash(data: &[u8]) -> Self {
+        BlockHash(sha256_double(data))
+    }
+}
+
+impl Hash160 {
+    pub fn hash(data: &[u8]) -> Self {
+        Hash160(hash160(data))
+    }
+}
+
+impl TapLeafHash {
+    /// tagged_hash("TapLeaf", leaf_version || compact_size(len) || script)
+    pub fn from_script(script: &[u8], leaf_version: u8) -> Self {
+        let mut hasher = Sha256Hasher::tagged("TapLeaf");
+        hasher.update(&[leaf_version]);
+        hasher.update(&serialize(&CompactSize(script.len() as u64)));
+        hasher.update(script);
+        TapLeafHash(hasher.finalize())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use hex;
+    use std::io::Write;
 
     #[test]
     fn test_hash() {
-        let z = sha256_double("my message");
+        let z = sha256_double(b"my message");
 
         assert_eq!(
             hex::encode(z),
             "0231c6f3d980a6b0fb7152f85cee7eb52bf92433d9919b9c5218cb08e79cce78"
         );
     }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            hex::encode(sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex::encode(sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex::encode(sha256_double(b"")),
+            "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456"
+        );
+    }
+
+    #[test]
+    fn test_hash160() {
+        assert_eq!(
+            hex::encode(ripemd160(b"abc")),
+            "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"
+        );
+
+        // compressed public key of the private key 1
+        let public_key =
+            hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                .unwrap();
+        assert_eq!(
+            hex::encode(hash160(&public_key)),
+            "751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+    }
+
+    #[test]
+    fn test_tagged_hash() {
+        let tag = sha256(b"BIP0340/challenge");
+        let expected = sha256(&[&tag[..], &tag[..], b"abc"].concat());
+
+        assert_eq!(tagged_hash("BIP0340/challenge", b"abc"), expected);
+    }
+
+    #[test]
+    fn test_streaming() {
+        let data = (0..=255u8).cycle().take(10_000).collect::<Vec<_>>();
+
+        let mut sha = Sha256Hasher::new();
+        let mut sha_double = Sha256dHasher::new();
+        let mut hash_160 = Hash160Hasher::new();
+        let mut tagged = Sha256Hasher::tagged("TapLeaf");
+        for chunk in data.chunks(333) {
+            sha.update(chunk);
+            sha_double.write_all(chunk).unwrap();
+            hash_160.update(chunk);
+            tagged.write_all(chunk).unwrap();
+        }
+
+        assert_eq!(sha.finalize(), sha256(&data));
+        assert_eq!(sha_double.finalize(), sha256_double(&data));
+        assert_eq!(hash_160.finalize(), hash160(&data));
+        assert_eq!(tagged.finalize(), tagged_hash("TapLeaf", &data));
+    }
+
+    #[test]
+    fn test_reversed_hex() {
+        // genesis block header and its coinbase transaction
+        let header = hex::decode(
+            "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c",
+        )
+        .unwrap();
+        let block_hash = BlockHash::hash(&header);
+        assert_eq!(
+            block_hash.to_string(),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(block_hash.as_byte_array()[31], 0x00);
+        assert_eq!(block_hash.to_string().parse::<BlockHash>(), Ok(block_hash));
+
+        let merkle_root = Txid::from_slice(&header[36..68]).unwrap();
+        assert_eq!(
+            merkle_root.to_string(),
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+        );
+        assert_eq!(
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b".parse::<Txid>(),
+            Ok(merkle_root)
+        );
+    }
+
+    #[test]
+    fn test_forward_hex() {
+        let script = hex::decode("410446ef0102d1ec5240f0d061a4246c1bdef63fc3dbab7733052fbbf0ecd8f41fc26bf049ebb4f9527f374280259e7cfa99c48b0e3f39c51347a19a5819651503a5ac").unwrap();
+        let script_hash = Hash160::hash(&script);
+        assert_eq!(
+            script_hash.to_string(),
+            "8292bcfbef1884f73c813dfe9c82fd7e814291ea"
+        );
+        assert_eq!(script_hash.to_byte_array(), hash160(&script));
+        assert_eq!(
+            "8292bcfbef1884f73c813dfe9c82fd7e814291ea".parse::<Hash160>(),
+            Ok(script_hash)
+        );
+
+        let tapscript =
+            hex::decode("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")
+                .unwrap();
+        assert_eq!(
+            TapLeafHash::from_script(&tapscript, 0xc0).to_string(),
+            "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"
+        );
+    }
+
+    #[test]
+    fn test_hash_newtype_errors() {
+        assert_eq!(
+            "8292bcfb".parse::<Hash160>(),
+            Err(Error::InvalidLength {
+                expected: 20,
+                found: 4
+            })
+        );
+        assert!(matches!("xyz".parse::<Txid>(), Err(Error::InvalidHex(_))));
+        assert_eq!(
+            Sha256d::from_slice(&[0u8; 33]),
+            Err(Error::InvalidLength {
+                expected: 32,
+                found: 33
+            })
+        );
+        assert_eq!(
+            Sha256d::hash(b"my message").to_byte_array(),
+            sha256_double(b"my message")
+        );
+    }
 }
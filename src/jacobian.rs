@@ -0,0 +1,335 @@
+#![allow(dead_code)]
+
+use crate::finite_field::Field;
+use crate::point::Point;
+
+/// Point in Jacobian coordinates (X:Y:Z), representing the affine point
+/// (X/Z^2, Y/Z^3). Additions and doublings don't need field inversions, so
+/// long chains of operations only pay for a single inversion when the result
+/// is converted back with `to_affine`.
+#[derive(Clone, Debug)]
+pub enum JacobianPoint<F: Field> {
+    Coor { a: F, b: F, x: F, y: F, z: F },
+    Zero,
+}
+
+fn twice<F: Field>(v: &F) -> F {
+    v.clone() + v.clone()
+}
+
+impl<F: Field> From<Point<F>> for JacobianPoint<F> {
+    fn from(point: Point<F>) -> Self {
+        match point {
+            Point::Coor { a, b, x, y } => {
+                let z = x.one_element();
+                JacobianPoint::Coor { a, b, x, y, z }
+            }
+            Point::Zero => JacobianPoint::Zero,
+        }
+    }
+}
+
+impl<F: Field> JacobianPoint<F> {
+    pub fn is_zero(&self) -> bool {
+        matches!(self, JacobianPoint::Zero)
+    }
+
+    pub fn to_affine(&self) -> Point<F> {
+        match self {
+            JacobianPoint::Coor { a, b, x, y, z } => {
+                let z_inv = z.one_element() / z.clone();
+                let z_inv_2 = z_inv.clone() * z_inv.clone();
+                let z_inv_3 = z_inv_2.clone() * z_inv;
+
+                Point::Coor {
+                    a: a.clone(),
+                    b: b.clone(),
+                    x: x.clone() * z_inv_2,
+                    y: y.clone() * z_inv_3,
+                }
+            }
+            JacobianPoint::Zero => Point::Zero,
+        }
+    }
+
+    pub fn negate(&self) -> Self {
+        match self {
+            JacobianPoint::Coor { a, b, x, y, z } => JacobianPoint::Coor {
+                a: a.clone(),
+                b: b.clone(),
+                x: x.clone(),
+                y: y.zero_element() - y.clone(),
+                z: z.clone(),
+            },
+            JacobianPoint::Zero => JacobianPoint::Zero,
+        }
+    }
+
+    pub fn double(&self) -> Self {
+        match self {
+            JacobianPoint::Coor { a, b, x, y, z } => {
+                if *y == y.zero_element() {
+                    return JacobianPoint::Zero;
+                }
+
+                let xx = x.clone() * x.clone();
+                let yy = y.clone() * y.clone();
+                let zz = z.clone() * z.clone();
+
+                // s = 4 * x * y^2, m = 3 * x^2 + a * z^4
+                let s = twice(&twice(&(x.clone() * yy.clone())));
+                let m = twice(&xx) + xx + a.clone() * zz.clone() * zz;
+
+                let x_res = m.clone() * m.clone() - twice(&s);
+                let y_res = m * (s - x_res.clone()) - twice(&twice(&twice(&(yy.clone() * yy))));
+                let z_res = twice(&(y.clone() * z.clone()));
+
+                JacobianPoint::Coor {
+                    a: a.clone(),
+                    b: b.clone(),
+                    x: x_res,
+                    y: y_res,
+                    z: z_res,
+                }
+            }
+            JacobianPoint::Zero => JacobianPoint::Zero,
+        }
+    }
+
+    pub fn add(&self, rhs: &JacobianPoint<F>) -> Self {
+        match (self, rhs) {
+            (JacobianPoint::Zero, _) => rhs.clone(),
+            (_, JacobianPoint::Zero) => self.clone(),
+            (
+                JacobianPoint::Coor { a, b, x, y, z },
+                JacobianPoint::Coor {
+                    x: x_rhs,
+                    y: y_rhs,
+                    z: z_rhs,
+                    ..
+                },
+            ) => {
+                let zz = z.clone() * z.clone();
+                let zz_rhs = z_rhs.clone() * z_rhs.clone();
+
+                let u = x.clone() * zz_rhs.clone();
+                let u_rhs = x_rhs.clone() * zz.clone();
+                let s = y.clone() * z_rhs.clone() * zz_rhs;
+                let s_rhs = y_rhs.clone() * z.clone() * zz;
+
+                let z_res = z.clone() * z_rhs.clone();
+                Self::add_with(a, b, u, u_rhs, s, s_rhs, z_res, self)
+            }
+        }
+    }
+
+    /// Mixed addition: the right hand side is affine (Z = 1), which saves
+    /// a few multiplications compared to `add`.
+    pub fn add_affine(&self, rhs: &Point<F>) -> Self {
+        match (self, rhs) {
+            (_, Point::Zero) => self.clone(),
+            (JacobianPoint::Zero, _) => JacobianPoint::from(rhs.clone()),
+            (
+                JacobianPoint::Coor { a, b, x, y, z },
+                Point::Coor {
+                    x: x_rhs, y: y_rhs, ..
+                },
+            ) => {
+                let zz = z.clone() * z.clone();
+
+                let u_rhs = x_rhs.clone() * zz.clone();
+                let s_rhs = y_rhs.clone() * z.clone() * zz;
+
+                Self::add_with(a, b, x.clone(), u_rhs, y.clone(), s_rhs, z.clone(), self)
+            }
+        }
+    }
+
+    // Shared tail of `add` and `add_affine`. `u`/`s` are both x and y
+    // coordinates brought to the same denominator and `z` the product of
+    // the input Z coordinates.
+    #[allow(clippy::too_many_arguments)]
+    fn add_with(a: &F, b: &F, u: F, u_rhs: F, s: F, s_rhs: F, z: F, lhs: &Self) -> Self {
+        if u == u_rhs {
+            if s == s_rhs {
+                return lhs.double();
+            }
+            return JacobianPoint::Zero;
+        }
+
+        let h = u_rhs - u.clone();
+        let r = s_rhs - s.clone();
+        let hh = h.clone() * h.clone();
+        let hhh = hh.clone() * h.clone();
+        let v = u * hh;
+
+        let x_res = r.clone() * r.clone() - hhh.clone() - twice(&v);
+        let y_res = r * (v - x_res.clone()) - s * hhh;
+        let z_res = z * h;
+
+        JacobianPoint::Coor {
+            a: a.clone(),
+            b: b.clone(),
+            x: x_res,
+            y: y_res,
+            z: z_res,
+        }
+    }
+}
+
+/// Converts many points to affine coordinates sharing a single field
+/// inversion (Montgomery's trick).
+pub fn batch_to_affine<F: Field>(points: &[JacobianPoint<F>]) -> Vec<Point<F>> {
+    // prefix products of all the non-zero Z coordinates
+    let mut products: Vec<F> = Vec::with_capacity(points.len());
+    let mut acc: Option<F> = None;
+    for point in points {
+        if let JacobianPoint::Coor { z, .. } = point {
+            let next = match acc {
+                Some(acc) => acc * z.clone(),
+                None => z.clone(),
+            };
+            products.push(next.clone());
+            acc = Some(next);
+        }
+    }
+
+    let mut inv = match acc {
+        Some(acc) => acc.one_element() / acc,
+        None => return vec![Point::Zero; points.len()],
+    };
+
+    // walk backwards peeling one Z at a time off the accumulated inverse
+    let mut result = vec![Point::Zero; points.len()];
+    let mut index = products.len();
+    for (i, point) in points.iter().enumerate().rev() {
+        if let JacobianPoint::Coor { a, b, x, y, z } = point {
+            index -= 1;
+            let z_inv = if index == 0 {
+                inv.clone()
+            } else {
+                inv.clone() * products[index - 1].clone()
+            };
+            inv = inv * z.clone();
+
+            let z_inv_2 = z_inv.clone() * z_inv.clone();
+            let z_inv_3 = z_inv_2.clone() * z_inv;
+            result[i] = Point::Coor {
+                a: a.clone(),
+                b: b.clone(),
+                x: x.clone() * z_inv_2,
+                y: y.clone() * z_inv_3,
+            };
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finite_field::FiniteField;
+    use crate::secp256k1::Secp256k1Point;
+    use num_bigint::BigUint;
+
+    fn f223_points() -> Vec<Point<FiniteField>> {
+        let prime = 223;
+        let a = FiniteField::from((0, prime));
+        let b = FiniteField::from((7, prime));
+
+        [
+            (192, 105),
+            (17, 56),
+            (1, 193),
+            (47, 71),
+            (47, 152),
+            (143, 98),
+        ]
+        .iter()
+        .map(|(x, y)| {
+            let x = FiniteField::from((*x, prime));
+            let y = FiniteField::from((*y, prime));
+            Point::try_new(&a, &b, &x, &y).unwrap()
+        })
+        .chain([Point::Zero])
+        .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for p in f223_points() {
+            assert_eq!(JacobianPoint::from(p.clone()).to_affine(), p);
+        }
+    }
+
+    #[test]
+    fn test_add_and_double_match_affine() {
+        let points = f223_points();
+
+        for p in points.iter() {
+            let jp = JacobianPoint::from(p.clone());
+
+            assert_eq!(jp.double().to_affine(), p.clone() + p.clone());
+
+            for q in points.iter() {
+                let jq = JacobianPoint::from(q.clone());
+                let expected = p.clone() + q.clone();
+
+                assert_eq!(jp.add(&jq).to_affine(), expected);
+                assert_eq!(jp.add_affine(q).to_affine(), expected);
+
+                // same sums with non trivial Z coordinates on both sides
+                let jp2 = jp.double().add(&jp.negate());
+                let jq2 = jq.double().add(&jq.negate());
+                assert_eq!(jp2.add(&jq2).to_affine(), expected);
+                assert_eq!(jp2.add_affine(q).to_affine(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_negate() {
+        for p in f223_points() {
+            let jp = JacobianPoint::from(p);
+            assert!(jp.add(&jp.negate()).is_zero());
+        }
+    }
+
+    #[test]
+    fn test_batch_to_affine() {
+        let points = f223_points();
+        let jacobian = points
+            .iter()
+            .map(|p| {
+                let jp = JacobianPoint::from(p.clone());
+                jp.double().add(&jp.negate())
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(batch_to_affine(&jacobian), points);
+        assert_eq!(
+            batch_to_affine::<FiniteField>(&[JacobianPoint::Zero]),
+            vec![Point::Zero]
+        );
+        assert!(batch_to_affine::<FiniteField>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_secp256k1() {
+        let g = Secp256k1Point::generator();
+        let jg = JacobianPoint::from(g.clone());
+
+        let mut affine = g.clone();
+        let mut jacobian = jg.clone();
+        for _ in 0..20 {
+            affine = affine + g.clone();
+            jacobian = jacobian.add_affine(&g);
+        }
+
+        assert_eq!(jacobian.to_affine(), affine);
+        assert_eq!(
+            jg.double().double().to_affine(),
+            g.clone().scale(BigUint::from(4u32))
+        );
+    }
+}
@@ -1,6 +1,20 @@
-mod finite_field;
-mod hash;
-mod point;
-mod rfc6979;
-mod secp256k1;
-mod signature;
+pub mod address;
+pub mod base58;
+pub mod bech32;
+pub mod encoding;
+pub mod error;
+pub mod finite_field;
+pub mod glv;
+pub mod hash;
+pub mod jacobian;
+pub mod point;
+pub mod private_key;
+pub mod projective;
+pub mod rfc6979;
+pub mod scalar;
+pub mod schnorr;
+pub mod secp256k1;
+pub mod secp256k1_field;
+pub mod sighash;
+pub mod signature;
+pub mod transaction;
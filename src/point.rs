@@ -1,29 +1,31 @@
-use crate::finite_field::FiniteField;
-use hex;
-use num::{One, Zero};
+use crate::error::{Error, Result};
+use crate::finite_field::Field;
+use crate::jacobian::{batch_to_affine, JacobianPoint};
+use num::Zero;
 use num_bigint::{BigInt, BigUint};
 use std::fmt::{self, Debug};
-use std::ops::Add;
+use std::ops::{Add, Neg};
+
+// Window width of the wNAF representation used by `scale`
+const WNAF_WINDOW: usize = 5;
+
+// Above this many terms `multi_scale` switches from Strauss to Pippenger
+const STRAUSS_MAX_TERMS: usize = 8;
 
 #[derive(PartialEq, Clone)]
-pub enum Point {
-    Coor {
-        a: FiniteField,
-        b: FiniteField,
-        x: FiniteField,
-        y: FiniteField,
-    },
+pub enum Point<F: Field> {
+    Coor { a: F, b: F, x: F, y: F },
     Zero,
 }
 
-impl Debug for Point {
+impl<F: Field> Debug for Point<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Point::Coor { x, y, .. } = self {
             write!(
                 f,
                 "Point [x = {} y = {}]",
-                hex::encode(&x.number.to_bytes_be()),
-                hex::encode(&y.number.to_bytes_be())
+                hex::encode(x.to_biguint().to_bytes_be()),
+                hex::encode(y.to_biguint().to_bytes_be())
             )
         } else {
             write!(f, "Point = Zero")
@@ -31,9 +33,13 @@ impl Debug for Point {
     }
 }
 
-impl Point {
+impl<F: Field> Point<F> {
     #[allow(dead_code)]
-    fn new(a: &FiniteField, b: &FiniteField, x: &FiniteField, y: &FiniteField) -> Point {
+    fn new(a: &F, b: &F, x: &F, y: &F) -> Point<F> {
+        Self::try_new(a, b, x, y).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(a: &F, b: &F, x: &F, y: &F) -> Result<Point<F>> {
         let point = Point::Coor {
             a: a.clone(),
             b: b.clone(),
@@ -41,9 +47,12 @@ impl Point {
             y: y.clone(),
         };
         if !Self::is_on_curve(&point) {
-            panic!("({:?},{:?}) point is not in the curve", x, y);
+            return Err(Error::PointNotOnCurve {
+                x: x.to_biguint(),
+                y: y.to_biguint(),
+            });
         }
-        point
+        Ok(point)
     }
 
     #[allow(dead_code)]
@@ -52,14 +61,14 @@ impl Point {
     }
 
     #[allow(dead_code)]
-    fn is_zero(self) -> bool {
-        self == Point::Zero
+    fn is_zero(&self) -> bool {
+        *self == Point::Zero
     }
 
-    pub fn is_on_curve(p: &Point) -> bool {
+    pub fn is_on_curve(p: &Point<F>) -> bool {
         match p {
             Point::Coor { a, b, x, y } => {
-                return y.clone().pow(&BigInt::from(2u32))
+                y.clone().pow(&BigInt::from(2u32))
                     == x.clone().pow(&BigInt::from(3u32)) + a.clone() * x.clone() + b.clone()
             }
             Point::Zero => true,
@@ -69,28 +78,149 @@ impl Point {
     // TODO: take a reference for the scalar
     #[allow(dead_code)]
     pub fn scale(self, _scalar: BigUint) -> Self {
-        let mut current = self.clone();
-        let mut scalar = _scalar;
-        let mut result = Point::Zero;
+        if self == Point::Zero {
+            return Point::Zero;
+        }
+
+        let digits = wnaf(&_scalar, WNAF_WINDOW);
+        let table = self.odd_multiples(1 << (WNAF_WINDOW - 2));
+        let mut result = JacobianPoint::Zero;
 
-        while scalar != BigUint::zero() {
-            if &scalar & BigUint::one() != BigUint::zero() {
-                result = current.clone() + result;
+        for digit in digits.iter().rev() {
+            result = result.double();
+            if *digit > 0 {
+                result = result.add_affine(&table[*digit as usize / 2]);
+            } else if *digit < 0 {
+                result = result.add_affine(&-table[(-*digit) as usize / 2].clone());
             }
-            current = current.clone() + current;
-            scalar = scalar >> 1;
         }
-        return result;
+        result.to_affine()
     }
-}
 
-impl Add for Point {
-    type Output = Point;
+    /// Plain double-and-add over the bits of the scalar. `scale` gives the same
+    /// results faster, this one is kept as a reference for tests and benchmarks.
+    pub fn scale_double_and_add(self, scalar: BigUint) -> Self {
+        let mut result = JacobianPoint::Zero;
+
+        for i in (0..scalar.bits()).rev() {
+            result = result.double();
+            if scalar.bit(i) {
+                result = result.add_affine(&self);
+            }
+        }
+        result.to_affine()
+    }
+
+    // [P, 3P, 5P, ..., (2 * count - 1)P] in affine coordinates
+    pub(crate) fn odd_multiples(&self, count: usize) -> Vec<Point<F>> {
+        batch_to_affine(&self.odd_multiples_jacobian(count))
+    }
 
-    fn add(self, rhs: Point) -> Point {
+    fn odd_multiples_jacobian(&self, count: usize) -> Vec<JacobianPoint<F>> {
+        let point = JacobianPoint::from(self.clone());
+        let double = point.double();
+
+        let mut multiples = vec![point];
+        for i in 1..count {
+            multiples.push(multiples[i - 1].add(&double));
+        }
+        multiples
+    }
+
+    /// Computes k1 * P1 + k2 * P2 + ... + kn * Pn. A few terms are handled with
+    /// the Strauss-Shamir trick (all the wNAF expansions share one chain of
+    /// doublings) and larger batches with Pippenger's bucket method.
+    pub fn multi_scale(terms: &[(Point<F>, BigUint)]) -> Point<F> {
+        let terms = terms
+            .iter()
+            .filter(|(point, scalar)| *point != Point::Zero && !scalar.is_zero())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if terms.len() <= STRAUSS_MAX_TERMS {
+            Self::strauss(&terms)
+        } else {
+            Self::pippenger(&terms)
+        }
+    }
+
+    fn strauss(terms: &[(Point<F>, BigUint)]) -> Point<F> {
+        let count = 1 << (WNAF_WINDOW - 2);
+
+        let digits = terms
+            .iter()
+            .map(|(_, scalar)| wnaf(scalar, WNAF_WINDOW))
+            .collect::<Vec<_>>();
+
+        // one inversion for the tables of all the points
+        let multiples = terms
+            .iter()
+            .flat_map(|(point, _)| point.odd_multiples_jacobian(count))
+            .collect::<Vec<_>>();
+        let multiples = batch_to_affine(&multiples);
+        let tables = multiples.chunks(count).collect::<Vec<_>>();
+
+        let len = digits.iter().map(|d| d.len()).max().unwrap_or(0);
+        let mut result = JacobianPoint::Zero;
+
+        for i in (0..len).rev() {
+            result = result.double();
+            for (digits, table) in digits.iter().zip(tables.iter()) {
+                let digit = digits.get(i).copied().unwrap_or(0);
+                if digit > 0 {
+                    result = result.add_affine(&table[digit as usize / 2]);
+                } else if digit < 0 {
+                    result = result.add_affine(&-table[(-digit) as usize / 2].clone());
+                }
+            }
+        }
+        result.to_affine()
+    }
+
+    fn pippenger(terms: &[(Point<F>, BigUint)]) -> Point<F> {
+        // bucket width grows with the number of points, roughly log2(n)
+        let c = match terms.len() {
+            0..=31 => 3,
+            32..=127 => 5,
+            128..=1023 => 7,
+            _ => 9,
+        };
+        let bits = terms.iter().map(|(_, k)| k.bits()).max().unwrap_or(0);
+        let windows = bits.div_ceil(c);
+
+        let mut result = JacobianPoint::Zero;
+        for window in (0..windows).rev() {
+            for _ in 0..c {
+                result = result.double();
+            }
+
+            // bucket i accumulates the points whose window digit is i + 1
+            let mut buckets = vec![JacobianPoint::Zero; (1 << c) - 1];
+            for (point, scalar) in terms {
+                let digit = (0..c).fold(0usize, |acc, j| {
+                    acc | ((scalar.bit(window * c + j) as usize) << j)
+                });
+                if digit != 0 {
+                    buckets[digit - 1] = buckets[digit - 1].add_affine(point);
+                }
+            }
+
+            // sum_i (i + 1) * bucket_i with running sums
+            let mut running = JacobianPoint::Zero;
+            let mut window_sum = JacobianPoint::Zero;
+            for bucket in buckets.iter().rev() {
+                running = running.add(bucket);
+                window_sum = window_sum.add(&running);
+            }
+            result = result.add(&window_sum);
+        }
+        result.to_affine()
+    }
+
+    pub fn try_add(self, rhs: Point<F>) -> Result<Point<F>> {
         match (self.clone(), rhs.clone()) {
-            (Point::Zero, _) => return rhs,
-            (_, Point::Zero) => return self,
+            (Point::Zero, _) => Ok(rhs),
+            (_, Point::Zero) => Ok(self),
             (
                 Point::Coor { a, b, x, y },
                 Point::Coor {
@@ -102,14 +232,11 @@ impl Add for Point {
                 },
             ) => {
                 if a != a_rhs || b != b_rhs {
-                    panic!(
-                        "The points (x:{:?},y:{:?},a:{:?},b:{:?}) and (x:{:?},y:{:?},a:{:?},b:{:?}) belong to different curves",
-                        x, y, a, b, x_rhs, y_rhs, a_rhs, b_rhs
-                    );
+                    return Err(Error::DifferentCurves);
                 }
-                if x == x_rhs && y != y_rhs {
-                    Point::Zero
-                } else if self == rhs && y == x_rhs.clone().scale(BigUint::zero()) {
+                let point = if (x == x_rhs && y != y_rhs)
+                    || (self == rhs && y == x_rhs.clone().scale(BigUint::zero()))
+                {
                     Point::Zero
                 } else if x != x_rhs {
                     let s = (y_rhs.clone() - y.clone()) / (x_rhs.clone() - x.clone());
@@ -132,22 +259,74 @@ impl Add for Point {
                     let x_res =
                         s.clone().pow(&BigInt::from(2u32)) - x.clone().scale(BigUint::from(2u32));
                     let y_res = s * (x - x_res.clone()) - y;
-                    return Point::Coor {
+                    Point::Coor {
                         a,
                         b,
                         x: x_res,
                         y: y_res,
-                    };
-                }
+                    }
+                };
+                Ok(point)
             }
         }
     }
 }
 
+impl<F: Field> Add for Point<F> {
+    type Output = Point<F>;
+
+    fn add(self, rhs: Point<F>) -> Point<F> {
+        self.try_add(rhs).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl<F: Field> Neg for Point<F> {
+    type Output = Point<F>;
+
+    fn neg(self) -> Point<F> {
+        match self {
+            Point::Coor { a, b, x, y } => Point::Coor {
+                a,
+                b,
+                x,
+                y: y.zero_element() - y,
+            },
+            Point::Zero => Point::Zero,
+        }
+    }
+}
+
+/// Width-w non-adjacent form of a scalar, least significant digit first.
+/// Every non-zero digit is odd and lies in (-2^(w-1), 2^(w-1)), and any two
+/// non-zero digits are at least w positions apart.
+pub(crate) fn wnaf(scalar: &BigUint, w: usize) -> Vec<i8> {
+    let modulus = 1i64 << w;
+    let mut k = scalar.clone();
+    let mut digits = Vec::with_capacity(k.bits() as usize + 1);
+
+    while !k.is_zero() {
+        let mut digit = 0;
+        if k.bit(0) {
+            digit = k.iter_u64_digits().next().unwrap_or(0) as i64 & (modulus - 1);
+            if digit >= modulus / 2 {
+                digit -= modulus;
+            }
+            if digit > 0 {
+                k -= digit as u64;
+            } else {
+                k += (-digit) as u64;
+            }
+        }
+        digits.push(digit as i8);
+        k >>= 1;
+    }
+    digits
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use hex;
+    use crate::finite_field::FiniteField;
 
     #[test]
     fn test_on_curve() {
@@ -281,6 +460,37 @@ mod tests {
         assert_eq!(p1 + p2, p3);
     }
 
+    #[test]
+    fn test_try_new_and_try_add_errors() {
+        let prime = 223;
+        let a = FiniteField::from((0, prime));
+        let b = FiniteField::from((7, prime));
+
+        let x = FiniteField::from((200, prime));
+        let y = FiniteField::from((119, prime));
+
+        assert_eq!(
+            Point::try_new(&a, &b, &x, &y),
+            Err(Error::PointNotOnCurve {
+                x: BigUint::from(200u32),
+                y: BigUint::from(119u32)
+            })
+        );
+
+        let x = FiniteField::from((192, prime));
+        let y = FiniteField::from((105, prime));
+        let p1 = Point::try_new(&a, &b, &x, &y).unwrap();
+
+        // (1, 1) lies on y^2 = x^3 + 5 * x + 218 over F223
+        let a2 = FiniteField::from((5, prime));
+        let b2 = FiniteField::from((218, prime));
+        let one = FiniteField::from((1, prime));
+        let p2 = Point::try_new(&a2, &b2, &one, &one).unwrap();
+
+        assert_eq!(p1.clone().try_add(p2), Err(Error::DifferentCurves));
+        assert_eq!(p1.clone().try_add(Point::Zero), Ok(p1));
+    }
+
     #[test]
     fn test_scale() {
         let prime = 223;
@@ -320,6 +530,96 @@ mod tests {
         assert_eq!(p.scale(BigUint::from(21u32)), Point::Zero);
     }
 
+    #[test]
+    fn test_wnaf() {
+        for w in 2..=8 {
+            for k in [0u64, 1, 2, 7, 31, 255, 1000, 123456789, u64::MAX] {
+                let digits = wnaf(&BigUint::from(k), w);
+
+                let value = digits
+                    .iter()
+                    .rev()
+                    .fold(BigInt::zero(), |acc, d| acc * 2 + BigInt::from(*d));
+                assert_eq!(value, BigInt::from(k));
+
+                for (i, d) in digits.iter().enumerate() {
+                    if *d != 0 {
+                        assert!(d % 2 != 0 && (*d as i64).abs() < 1 << (w - 1));
+                        assert!(digits[i + 1..].iter().take(w - 1).all(|d| *d == 0));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_scale_matches_double_and_add() {
+        let prime = 223;
+        let a = FiniteField::from((0, prime));
+        let b = FiniteField::from((7, prime));
+        let x = FiniteField::from((15, prime));
+        let y = FiniteField::from((86, prime));
+        let p = Point::new(&a, &b, &x, &y);
+
+        for k in 0..50u32 {
+            assert_eq!(
+                p.clone().scale(BigUint::from(k)),
+                p.clone().scale_double_and_add(BigUint::from(k))
+            );
+        }
+
+        assert_eq!(-p.clone() + p, Point::Zero);
+    }
+
+    #[test]
+    fn test_multi_scale() {
+        let prime = 223;
+        let a = FiniteField::from((0, prime));
+        let b = FiniteField::from((7, prime));
+
+        let points = [(47, 71), (15, 86), (192, 105), (17, 56), (1, 193)]
+            .iter()
+            .map(|(x, y)| {
+                Point::new(
+                    &a,
+                    &b,
+                    &FiniteField::from((*x, prime)),
+                    &FiniteField::from((*y, prime)),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(Point::<FiniteField>::multi_scale(&[]), Point::Zero);
+
+        // both the Strauss (few terms) and Pippenger (many terms) paths
+        for count in [1, 2, 3, 8, 9, 40] {
+            let terms = (0..count)
+                .map(|i| {
+                    (
+                        points[i % points.len()].clone(),
+                        BigUint::from((i * 37 + 11) as u32),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let expected = terms
+                .iter()
+                .fold(Point::Zero, |acc, (p, k)| acc + p.clone().scale(k.clone()));
+
+            assert_eq!(Point::multi_scale(&terms), expected);
+        }
+
+        let terms = [
+            (points[0].clone(), BigUint::from(5u32)),
+            (Point::Zero, BigUint::from(3u32)),
+            (points[1].clone(), BigUint::zero()),
+        ];
+        assert_eq!(
+            Point::multi_scale(&terms),
+            points[0].clone().scale(BigUint::from(5u32))
+        );
+    }
+
     #[test]
     fn test_bitcoin_generator_point() {
         let prime = hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F")
@@ -0,0 +1,320 @@
+#![allow(dead_code)]
+
+use crate::address::Network;
+use crate::base58;
+use crate::error::{Error, Result, WifError};
+use crate::scalar::Scalar;
+use crate::secp256k1::Secp256k1Point;
+use num_bigint::BigUint;
+use std::fmt;
+use std::str::FromStr;
+use zeroize::Zeroize;
+
+/// Secret key e in [1, n - 1], with the network and the SEC compression of
+/// its public key P = e * G that WIF records.
+///
+/// The 32 bytes are wiped on drop. The range check and signing read them
+/// through `Scalar` without `BigUint` copies of the secret.
+#[derive(Clone)]
+pub struct PrivateKey {
+    secret: [u8; 32],
+    compressed: bool,
+    network: Network,
+}
+
+impl PrivateKey {
+    /// Mainnet key with a compressed public key.
+    pub fn new(secret: &BigUint) -> Result<PrivateKey> {
+        let mut bytes = secret.to_bytes_be();
+        if bytes.len() > 32 {
+            bytes.zeroize();
+            return Err(Error::InvalidSecretKey);
+        }
+
+        let mut buffer = [0u8; 32];
+        buffer[32 - bytes.len()..].copy_from_slice(&bytes);
+        let key = Self::from_bytes(&buffer);
+        bytes.zeroize();
+        buffer.zeroize();
+        key
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<PrivateKey> {
+        let secret: [u8; 32] = bytes.try_into().map_err(|_| Error::InvalidLength {
+            expected: 32,
+            found: bytes.len(),
+        })?;
+
+        let key = PrivateKey {
+            secret,
+            compressed: true,
+            network: Network::Mainnet,
+        };
+        match Scalar::from_canonical_bytes_be(&key.secret) {
+            Some(scalar) if !scalar.is_zero() => Ok(key),
+            _ => Err(Error::InvalidSecretKey),
+        }
+    }
+
+    pub fn random() -> PrivateKey {
+        Self::try_random().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Key drawn from the OS random number generator, retrying the
+    /// negligible fraction of 32 byte strings that are not in [1, n - 1].
+    pub fn try_random() -> Result<PrivateKey> {
+        let mut bytes = [0u8; 32];
+        loop {
+            getrandom::fill(&mut bytes).map_err(|err| Error::RandomSource(err.to_string()))?;
+            let key = Self::from_bytes(&bytes);
+            bytes.zeroize();
+            if let Ok(key) = key {
+                return Ok(key);
+            }
+        }
+    }
+
+    pub fn with_network(mut self, network: Network) -> PrivateKey {
+        self.network = network;
+        self
+    }
+
+    pub fn with_compression(mut self, compressed: bool) -> PrivateKey {
+        self.compressed = compressed;
+        self
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    pub fn compressed(&self) -> bool {
+        self.compressed
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.secret
+    }
+
+    /// The secret as a constant-time scalar, for signing.
+    pub fn scalar(&self) -> Scalar {
+        Scalar::from_bytes_be(&self.secret)
+    }
+
+    pub fn public_key(&self) -> Secp256k1Point {
+        Secp256k1Point::mul_generator(&self.scalar())
+    }
+
+    /// SEC encoding of the public key with the key's compression.
+    pub fn public_key_sec(&self) -> Vec<u8> {
+        self.public_key().to_sec(self.compressed)
+    }
+
+    /// Wallet Import Format: base58check of the version byte, the secret
+    /// and a 0x01 suffix when the public key is compressed.
+    pub fn to_wif(&self) -> String {
+        let prefix = match self.network {
+            Network::Mainnet => 0x80,
+            Network::Testnet | Network::Signet | Network::Regtest => 0xef,
+        };
+
+        let mut data = Vec::with_capacity(34);
+        data.push(prefix);
+        data.extend_from_slice(&self.secret);
+        if self.compressed {
+            data.push(0x01);
+        }
+
+        let wif = base58::encode_check(&data);
+        data.zeroize();
+        wif
+    }
+
+    /// Parses a WIF key. The test networks share the version byte, so
+    /// their keys come out as `Testnet`.
+    pub fn from_wif(wif: &str) -> Result<PrivateKey> {
+        let mut data = base58::decode_check(wif)?;
+        let key = Self::from_wif_data(&data);
+        data.zeroize();
+        key
+    }
+
+    fn from_wif_data(data: &[u8]) -> Result<PrivateKey> {
+        let compressed = match data.len() {
+            33 => false,
+            34 if data[33] == 0x01 => true,
+            34 => return Err(WifError::InvalidCompressionFlag(data[33]).into()),
+            length => return Err(WifError::InvalidLength(length).into()),
+        };
+        let network = match data[0] {
+            0x80 => Network::Mainnet,
+            0xef => Network::Testnet,
+            prefix => return Err(WifError::UnknownPrefix(prefix).into()),
+        };
+
+        Ok(Self::from_bytes(&data[1..33])?
+            .with_network(network)
+            .with_compression(compressed))
+    }
+}
+
+impl Drop for PrivateKey {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl PartialEq for PrivateKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.secret == other.secret
+            && self.compressed == other.compressed
+            && self.network == other.network
+    }
+}
+
+impl Eq for PrivateKey {}
+
+// Never prints the secret
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PrivateKey")
+            .field("compressed", &self.compressed)
+            .field("network", &self.network)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FromStr for PrivateKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PrivateKey> {
+        Self::from_wif(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::Address;
+    use num::Zero;
+
+    #[test]
+    fn test_range() {
+        let n = Secp256k1Point::n();
+
+        assert_eq!(
+            PrivateKey::new(&BigUint::zero()),
+            Err(Error::InvalidSecretKey)
+        );
+        assert_eq!(PrivateKey::new(&n), Err(Error::InvalidSecretKey));
+        assert!(PrivateKey::new(&(&n - 1u32)).is_ok());
+        assert_eq!(
+            PrivateKey::new(&(BigUint::from(1u32) << 256)),
+            Err(Error::InvalidSecretKey)
+        );
+        assert_eq!(
+            PrivateKey::from_bytes(&n.to_bytes_be()),
+            Err(Error::InvalidSecretKey)
+        );
+        assert_eq!(
+            PrivateKey::from_bytes(&[0xff; 32]),
+            Err(Error::InvalidSecretKey)
+        );
+        assert_eq!(
+            PrivateKey::from_bytes(&[1u8; 31]),
+            Err(Error::InvalidLength {
+                expected: 32,
+                found: 31
+            })
+        );
+
+        let key = PrivateKey::new(&BigUint::from(1u32)).unwrap();
+        assert_eq!(key.public_key(), Secp256k1Point::generator());
+        assert_eq!(key.to_bytes()[31], 1);
+    }
+
+    #[test]
+    fn test_wif() {
+        let one = PrivateKey::new(&BigUint::from(1u32)).unwrap();
+        assert_eq!(
+            one.to_wif(),
+            "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn"
+        );
+        assert_eq!(
+            one.clone().with_compression(false).to_wif(),
+            "5HpHagT65TZzG1PH3CSu63k8DbpvD8s5ip4nEB3kEsreAnchuDf"
+        );
+
+        let key: PrivateKey = "5Hx15HFGyep2CfPxsJKe2fXJsCVn5DEiyoeGGF6JZjGbTRnqfiD"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            hex::encode(key.to_bytes()),
+            "1184cd2cdd640ca42cfc3a091c51d549b2f016d454b2774019c2b2d2e08529fd"
+        );
+        assert!(!key.compressed());
+
+        // testnet compressed
+        let wif = "cVt4o7BGAig1UXywgGSmARhxMdzP5qvQsxKkSsc1XEkw3tDTQFpy";
+        let key = PrivateKey::from_wif(wif).unwrap();
+        assert_eq!(key.network(), Network::Testnet);
+        assert!(key.compressed());
+        assert_eq!(key.to_wif(), wif);
+        assert_eq!(
+            Address::p2pkh(&key.public_key(), key.compressed(), key.network()).to_string(),
+            "mqwpxxvfv3QbM8PU8uBx2jaNt9btQqvQNx"
+        );
+
+        // mainnet uncompressed
+        let wif = "5JYkZjmN7PVMjJUfJWfRFwtuXTGB439XV6faajeHPAM9Z2PT2R3";
+        let key = PrivateKey::from_wif(wif).unwrap();
+        assert_eq!(key.network(), Network::Mainnet);
+        assert!(!key.compressed());
+        assert_eq!(key.to_wif(), wif);
+        assert_eq!(
+            hex::encode(key.public_key_sec()),
+            "042e58afe51f9ed8ad3cc7897f634d881fdbe49a81564629ded8156bebd2ffd1af191923a2964c177f5b5923ae500fca49e99492d534aa3759d6b25a8bc971b133"
+        );
+        assert_eq!(
+            Address::p2pkh(&key.public_key(), false, key.network()).to_string(),
+            "1GhQvF6dL8xa6wBxLnWmHcQsurx9RxiMc8"
+        );
+    }
+
+    #[test]
+    fn test_invalid_wif() {
+        let secret = [7u8; 32];
+        let wif = |data: &[&[u8]]| base58::encode_check(&data.concat());
+
+        assert_eq!(
+            PrivateKey::from_wif(&wif(&[&[0x80], &secret, &[0x02]])),
+            Err(WifError::InvalidCompressionFlag(0x02).into())
+        );
+        assert_eq!(
+            PrivateKey::from_wif(&wif(&[&[0x80], &secret[1..]])),
+            Err(WifError::InvalidLength(32).into())
+        );
+        assert_eq!(
+            PrivateKey::from_wif(&wif(&[&[0x00], &secret, &[0x01]])),
+            Err(WifError::UnknownPrefix(0x00).into())
+        );
+        assert_eq!(
+            PrivateKey::from_wif(&wif(&[&[0x80], &[0u8; 32], &[0x01]])),
+            Err(Error::InvalidSecretKey)
+        );
+        assert!(matches!(
+            PrivateKey::from_wif("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWm"),
+            Err(Error::InvalidBase58(_))
+        ));
+    }
+
+    #[test]
+    fn test_random() {
+        let a = PrivateKey::random();
+        let b = PrivateKey::try_random().unwrap();
+
+        assert_ne!(a, b);
+        assert_eq!(PrivateKey::from_wif(&a.to_wif()), Ok(a.clone()));
+        assert!(!format!("{:?}", a).contains(&hex::encode(a.to_bytes())));
+    }
+}
@@ -0,0 +1,272 @@
+#![allow(dead_code)]
+
+use crate::point::Point;
+use crate::scalar::Scalar;
+use crate::secp256k1::Secp256k1Point;
+use crate::secp256k1_field::Secp256k1Field;
+
+/// secp256k1 point in homogeneous projective coordinates (X:Y:Z), i.e. the
+/// affine point (X/Z, Y/Z). The point at infinity is (0:1:0), so unlike
+/// `Point` and `JacobianPoint` there is no separate variant for it.
+///
+/// Additions use the complete formulas for a = 0 curves from Renes, Costello
+/// and Batina ("Complete addition formulas for prime order elliptic curves",
+/// algorithms 7 and 9). They have no exceptional cases, so together with the
+/// branch-free field arithmetic the running time doesn't depend on the
+/// values being added, which is what secret scalar multiplication needs.
+#[derive(Clone, Copy, Debug)]
+pub struct ProjectivePoint {
+    x: Secp256k1Field,
+    y: Secp256k1Field,
+    z: Secp256k1Field,
+}
+
+// 3 * b
+fn b3() -> Secp256k1Field {
+    Secp256k1Field::from(21)
+}
+
+// 1 when a == b, 0 otherwise, without branching
+fn ct_eq(a: u8, b: u8) -> u64 {
+    ((a ^ b) as u64).wrapping_sub(1) >> 63
+}
+
+impl ProjectivePoint {
+    pub fn identity() -> Self {
+        ProjectivePoint {
+            x: Secp256k1Field::zero(),
+            y: Secp256k1Field::one(),
+            z: Secp256k1Field::zero(),
+        }
+    }
+
+    pub fn from_affine(point: &Secp256k1Point) -> Self {
+        match point {
+            Point::Coor { x, y, .. } => ProjectivePoint {
+                x: *x,
+                y: *y,
+                z: Secp256k1Field::one(),
+            },
+            Point::Zero => Self::identity(),
+        }
+    }
+
+    pub fn to_affine(&self) -> Secp256k1Point {
+        if self.z.is_zero() {
+            return Point::Zero;
+        }
+
+        let z_inv = self.z.invert();
+        Point::Coor {
+            a: Secp256k1Point::a(),
+            b: Secp256k1Point::b(),
+            x: self.x * z_inv,
+            y: self.y * z_inv,
+        }
+    }
+
+    pub fn add(&self, rhs: &ProjectivePoint) -> Self {
+        let (x1, y1, z1) = (self.x, self.y, self.z);
+        let (x2, y2, z2) = (rhs.x, rhs.y, rhs.z);
+
+        let mut t0 = x1 * x2;
+        let mut t1 = y1 * y2;
+        let mut t2 = z1 * z2;
+        let mut t3 = (x1 + y1) * (x2 + y2);
+        let mut t4 = t0 + t1;
+        t3 = t3 - t4;
+        t4 = (y1 + z1) * (y2 + z2);
+        let mut x3 = t1 + t2;
+        t4 = t4 - x3;
+        x3 = (x1 + z1) * (x2 + z2);
+        let mut y3 = t0 + t2;
+        y3 = x3 - y3;
+        x3 = t0 + t0;
+        t0 = x3 + t0;
+        t2 = b3() * t2;
+        let mut z3 = t1 + t2;
+        t1 = t1 - t2;
+        y3 = b3() * y3;
+        x3 = t4 * y3;
+        t2 = t3 * t1;
+        x3 = t2 - x3;
+        y3 = y3 * t0;
+        t1 = t1 * z3;
+        y3 = t1 + y3;
+        t0 = t0 * t3;
+        z3 = z3 * t4;
+        z3 = z3 + t0;
+
+        ProjectivePoint {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    pub fn double(&self) -> Self {
+        let (x, y, z) = (self.x, self.y, self.z);
+
+        let mut t0 = y * y;
+        let mut z3 = t0 + t0;
+        z3 = z3 + z3;
+        z3 = z3 + z3;
+        let mut t1 = y * z;
+        let mut t2 = z * z;
+        t2 = b3() * t2;
+        let mut x3 = t2 * z3;
+        let mut y3 = t0 + t2;
+        z3 = t1 * z3;
+        t1 = t2 + t2;
+        t2 = t1 + t2;
+        t0 = t0 - t2;
+        y3 = t0 * y3;
+        y3 = x3 + y3;
+        t1 = x * y;
+        x3 = t0 * t1;
+        x3 = x3 + x3;
+
+        ProjectivePoint {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    /// Returns `b` when `choice` is 1 and `a` when it is 0.
+    pub fn conditional_select(a: &Self, b: &Self, choice: u64) -> Self {
+        ProjectivePoint {
+            x: Secp256k1Field::conditional_select(&a.x, &b.x, choice),
+            y: Secp256k1Field::conditional_select(&a.y, &b.y, choice),
+            z: Secp256k1Field::conditional_select(&a.z, &b.z, choice),
+        }
+    }
+
+    /// Swaps `a` and `b` when `choice` is 1.
+    pub fn conditional_swap(a: &mut Self, b: &mut Self, choice: u64) {
+        let new_a = Self::conditional_select(a, b, choice);
+        let new_b = Self::conditional_select(b, a, choice);
+        *a = new_a;
+        *b = new_b;
+    }
+
+    /// Reads `table[index]` touching every entry, so the memory access
+    /// pattern doesn't reveal the index.
+    pub fn lookup(table: &[ProjectivePoint], index: u8) -> Self {
+        let mut result = Self::identity();
+        for (i, entry) in table.iter().enumerate() {
+            result = Self::conditional_select(&result, entry, ct_eq(i as u8, index));
+        }
+        result
+    }
+}
+
+/// Montgomery ladder over all 256 bits of k: every iteration does exactly one
+/// addition and one doubling, and the bit only selects, without branching,
+/// which of the two registers gets doubled.
+pub fn montgomery_ladder(point: &Secp256k1Point, k: &Scalar) -> Secp256k1Point {
+    let k = k.to_bytes_le();
+
+    let mut r0 = ProjectivePoint::identity();
+    let mut r1 = ProjectivePoint::from_affine(point);
+
+    for i in (0..256).rev() {
+        let bit = ((k[i / 8] >> (i % 8)) & 1) as u64;
+
+        ProjectivePoint::conditional_swap(&mut r0, &mut r1, bit);
+        r1 = r0.add(&r1);
+        r0 = r0.double();
+        ProjectivePoint::conditional_swap(&mut r0, &mut r1, bit);
+    }
+
+    r0.to_affine()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_add_and_double_match_affine() {
+        let g = Secp256k1Point::generator();
+        let points = [
+            Point::Zero,
+            g.clone(),
+            g.clone() + g.clone(),
+            -g.clone(),
+            g.clone().scale(BigUint::from(12345u32)),
+        ];
+
+        for p in points.iter() {
+            let pp = ProjectivePoint::from_affine(p);
+
+            assert_eq!(pp.double().to_affine(), p.clone() + p.clone());
+            assert_eq!(pp.add(&pp).to_affine(), p.clone() + p.clone());
+
+            for q in points.iter() {
+                let pq = ProjectivePoint::from_affine(q);
+                assert_eq!(pp.add(&pq).to_affine(), p.clone() + q.clone());
+
+                // non trivial Z coordinates
+                let pp3 = pp.double().add(&pp);
+                let pq3 = pq.double().add(&pq);
+                let expected =
+                    p.clone().scale(BigUint::from(3u32)) + q.clone().scale(BigUint::from(3u32));
+                assert_eq!(pp3.add(&pq3).to_affine(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_and_swap() {
+        let g = Secp256k1Point::generator();
+        let table = (0..16u32)
+            .map(|i| ProjectivePoint::from_affine(&g.clone().scale(BigUint::from(i))))
+            .collect::<Vec<_>>();
+
+        for i in 0..16u8 {
+            assert_eq!(
+                ProjectivePoint::lookup(&table, i).to_affine(),
+                g.clone().scale(BigUint::from(i))
+            );
+        }
+
+        let mut a = table[2];
+        let mut b = table[3];
+        ProjectivePoint::conditional_swap(&mut a, &mut b, 0);
+        assert_eq!(a.to_affine(), table[2].to_affine());
+        ProjectivePoint::conditional_swap(&mut a, &mut b, 1);
+        assert_eq!(a.to_affine(), table[3].to_affine());
+        assert_eq!(b.to_affine(), table[2].to_affine());
+    }
+
+    #[test]
+    fn test_montgomery_ladder() {
+        let p = Secp256k1Point::generator().scale(BigUint::from(987654321u32));
+        let scalars = [
+            BigUint::from(0u32),
+            BigUint::from(1u32),
+            BigUint::from(2u32),
+            BigUint::from(12345u32),
+            Secp256k1Point::n() - 1u32,
+            Secp256k1Point::n(),
+            BigUint::from_bytes_be(
+                &hex::decode("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721")
+                    .unwrap(),
+            ),
+        ];
+
+        for k in scalars {
+            assert_eq!(
+                montgomery_ladder(&p, &Scalar::from_biguint(&k)),
+                p.clone().scale(k.clone())
+            );
+        }
+
+        assert_eq!(
+            montgomery_ladder(&Point::Zero, &Scalar::from(5)),
+            Point::Zero
+        );
+    }
+}
@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
 use hmac::{Hmac, Mac};
-use num::{One, Zero};
+use num::Zero;
 use num_bigint::BigUint;
 use sha2::Sha256;
 use std::cmp::min;
@@ -12,23 +12,50 @@ pub fn generate_k<const N: usize, const K: usize>(
     q: &[u8; N],
 ) -> BigUint {
     let q_bi = BigUint::from_bytes_be(q);
+    let t = candidate::<N, K>(z, e, |t| {
+        let k = bits_2_int(t, q_bi.bits());
+        k != BigUint::zero() && k < q_bi
+    });
+    bits_2_int(&t, q_bi.bits())
+}
+
+/// `generate_k` for a q of exactly 8 * N bits, like the secp256k1 order, so
+/// that bits2int takes the candidates as they are. The secret nonce stays
+/// a byte array and the range check looks at every byte.
+pub fn generate_k_bytes<const N: usize, const K: usize>(
+    z: &[u8; N],
+    e: &[u8; N],
+    q: &[u8; N],
+) -> [u8; N] {
+    assert!(q[0] & 0x80 != 0, "q must have {} bits", 8 * N);
+    candidate::<N, K>(z, e, |t| in_range(t, q))
+}
 
+// Whether 0 < t < q, computed with a borrow through all the bytes instead of
+// stopping at the first one that differs
+fn in_range<const N: usize>(t: &[u8; N], q: &[u8; N]) -> bool {
+    let mut borrow = 0u16;
+    let mut bits = 0u8;
+    for i in (0..N).rev() {
+        borrow = ((t[i] as u16).wrapping_sub(q[i] as u16 + borrow) >> 15) & 1;
+        bits |= t[i];
+    }
+    (borrow == 1) & (bits != 0)
+}
+
+// First candidate T of the HMAC_DRBG that `accept` takes as the nonce
+fn candidate<const N: usize, const K: usize>(
+    z: &[u8; N],
+    e: &[u8; N],
+    mut accept: impl FnMut(&[u8; N]) -> bool,
+) -> [u8; N] {
     let mut k = [0x00; K];
     let mut v = [0x01; K];
 
     for i in 0..=1 {
         // K = HMAC_K(V || 0x00 || int2octets(x) || bits2octets(h1))
         // V = HMAC_K(V)
-        k = hmac(
-            &k,
-            &[
-                &v[..],
-                &[i][..],
-                &int_2_octets::<N>(BigUint::from_bytes_be(e))[..],
-                &z[..],
-            ]
-            .concat(),
-        );
+        k = hmac(&k, &[&v[..], &[i][..], &e[..], &z[..]].concat());
 
         v = hmac(&k, &v[..]);
     }
@@ -45,9 +72,8 @@ pub fn generate_k<const N: usize, const K: usize>(
             toff += cc;
         }
 
-        let k_candidate = bits_2_int(&t, q_bi.bits());
-        if k_candidate != BigUint::zero() && k_candidate < q_bi {
-            return k_candidate;
+        if accept(&t) {
+            return t;
         }
 
         // K = HMAC_K(V || 0x00)
@@ -75,7 +101,7 @@ fn hmac<const K: usize>(k: &[u8; K], h: &[u8]) -> [u8; K] {
 }
 
 fn bits_2_int(vb: &[u8], qlen: u64) -> BigUint {
-    let mut v = BigUint::from_bytes_be(&vb);
+    let mut v = BigUint::from_bytes_be(vb);
     let vlen = vb.len() * 8;
     if vlen > qlen as usize {
         v >>= vlen - qlen as usize;
@@ -83,7 +109,7 @@ fn bits_2_int(vb: &[u8], qlen: u64) -> BigUint {
     v
 }
 
-fn int_2_octets<const N: usize>(n: BigUint) -> [u8; N] {
+pub fn int_2_octets<const N: usize>(n: BigUint) -> [u8; N] {
     let n = n.to_bytes_be();
     let mut buffer = [0u8; N];
 
@@ -99,9 +125,9 @@ fn int_2_octets<const N: usize>(n: BigUint) -> [u8; N] {
     buffer
 }
 
-fn bits_2_octets<const N: usize>(n: &[u8], q: &[u8]) -> [u8; N] {
+pub fn bits_2_octets<const N: usize>(n: &[u8], q: &[u8]) -> [u8; N] {
     let q = BigUint::from_bytes_be(q);
-    let mut n = bits_2_int(&n, q.bits());
+    let mut n = bits_2_int(n, q.bits());
 
     if n >= q {
         n -= q;
@@ -111,6 +137,7 @@ fn bits_2_octets<const N: usize>(n: &[u8], q: &[u8]) -> [u8; N] {
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod tests {
     use super::*;
     use crate::hash::sha256;
@@ -204,6 +231,18 @@ mod tests {
         assert_eq!(result, "023af4074c90a02b3fe61d286d5c87f425e6bdd81b");
     }
 
+    #[test]
+    fn test_in_range() {
+        let q = [0x80, 0x00, 0x05];
+        assert!(in_range(&[0x00, 0x00, 0x01], &q));
+        assert!(in_range(&[0x80, 0x00, 0x04], &q));
+        assert!(in_range(&[0x7f, 0xff, 0xff], &q));
+        assert!(!in_range(&[0x00, 0x00, 0x00], &q));
+        assert!(!in_range(&[0x80, 0x00, 0x05], &q));
+        assert!(!in_range(&[0x80, 0x01, 0x00], &q));
+        assert!(!in_range(&[0xff, 0xff, 0xff], &q));
+    }
+
     #[test]
     fn test_generate_k() {
         let q: Vec<u8> = hex::decode("04000000000000000000020108a2e0cc0d99f8a5ef").unwrap();
@@ -235,7 +274,7 @@ mod tests {
     fn test_vector_dsa_1024_bits() {
         let q = &hex::decode("996f967f6c8e388d9e28d01e205fba957a5698b1").unwrap();
         let e = hex::decode("411602cb19a6ccc34494d79d98ef1e7ed5af25f7").unwrap();
-        let z = sha256("sample");
+        let z = sha256(b"sample");
 
         let qlen = BigUint::from_bytes_be(&q).bits();
         let rolen = (qlen + 7) >> 3;
@@ -264,7 +303,7 @@ mod tests {
         let e = "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721";
         let e = hex::decode(e).unwrap();
 
-        let z = sha256("sample");
+        let z = sha256(b"sample");
 
         let qlen = BigUint::from_bytes_be(&q).bits();
         let rolen = (qlen + 7) >> 3;
@@ -292,7 +331,7 @@ mod tests {
         let e = "0028a04857f24c1c082df0d909c0e72f453f2e2340ccb071f0e389bca2575da19124198c57174929ad26e348cf63f78d28021ef5a9bf2d5cbeaf6b7ccb6c4da824dd5c82cfb24e11";
         let e = hex::decode(e).unwrap();
 
-        let z = sha256("sample");
+        let z = sha256(b"sample");
 
         let qlen = BigUint::from_bytes_be(&q).bits();
         let rolen = (qlen + 7) >> 3;
@@ -311,4 +350,50 @@ mod tests {
         let k_exp = "015c2c6b7d1a070274484774e558b69fdfa193bdb7a23f27c2cd24298ce1b22a6cc9b7fb8cabfd6cf7c6b1cf3251e5a1cddd16fbfed28de79935bb2c631b8b8ea9cc4bcc937e669e";
         assert_eq!(hex::encode(k.to_bytes_be()), k_exp);
     }
+
+    #[test]
+    fn test_vector_secp256k1() {
+        let q = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+        let q: [u8; 32] = hex::decode(q).unwrap().try_into().unwrap();
+        let q_minus_1 = BigUint::from_bytes_be(&q) - 1u32;
+
+        let cases = [
+            (
+                BigUint::from(1u32),
+                "Satoshi Nakamoto",
+                "8f8a276c19f4149656b280621e358cce24f5f52542772691ee69063b74f15d15",
+            ),
+            (
+                BigUint::from(1u32),
+                "All those moments will be lost in time, like tears in rain. Time to die...",
+                "38aa22d72376b4dbc472e06c3ba403ee0a394da63fc58d88686c611aba98d6b3",
+            ),
+            (
+                q_minus_1,
+                "Satoshi Nakamoto",
+                "33a19b60e25fb6f4435af53a3d42d493644827367e6453928554f43e49aa6f90",
+            ),
+            (
+                BigUint::parse_bytes(
+                    b"f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181",
+                    16,
+                )
+                .unwrap(),
+                "Alan Turing",
+                "525a82b70e67874398067543fd84c83d30c175fdc45fdeee082fe13b1d7cfdf1",
+            ),
+        ];
+
+        for (e, message, k_expected) in cases {
+            let e: [u8; 32] = int_2_octets(e);
+            let z: [u8; 32] = bits_2_octets(&sha256(message.as_bytes()), &q);
+
+            let k = generate_k::<32, 32>(&z, &e, &q);
+            assert_eq!(hex::encode(k.to_bytes_be()), k_expected);
+            assert_eq!(
+                hex::encode(generate_k_bytes::<32, 32>(&z, &e, &q)),
+                k_expected
+            );
+        }
+    }
 }
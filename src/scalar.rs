@@ -0,0 +1,393 @@
+#![allow(dead_code)]
+
+use crate::secp256k1_field::{adc, sbb, select};
+use num_bigint::BigUint;
+use std::fmt::{self, Debug};
+use std::ops::{Add, Mul, Neg, Sub};
+
+// n, the order of the secp256k1 group, as little-endian 64-bit limbs
+const N: [u64; 4] = [
+    0xBFD25E8CD0364141,
+    0xBAAEDCE6AF48A03B,
+    0xFFFFFFFFFFFFFFFE,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+// 2^256 mod n = 2^256 - n, a 129-bit number
+const C: [u64; 3] = [0x402DA1732FC9BEBF, 0x4551231950B75FC4, 0x1];
+
+// n - 2, the exponent used for inversions
+const N_MINUS_2: [u64; 4] = [
+    0xBFD25E8CD036413F,
+    0xBAAEDCE6AF48A03B,
+    0xFFFFFFFFFFFFFFFE,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+// (n - 1) / 2, the largest low-S value
+const N_HALF: [u64; 4] = [
+    0xDFE92F46681B20A0,
+    0x5D576E7357A4501D,
+    0xFFFFFFFFFFFFFFFF,
+    0x7FFFFFFFFFFFFFFF,
+];
+
+/// Integer mod n, the order of the secp256k1 group, stored in four 64-bit
+/// limbs and always fully reduced. It is the counterpart of `Secp256k1Field`
+/// for private keys and nonces: no operation branches on the value or has a
+/// running time that depends on it.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct Scalar {
+    limbs: [u64; 4],
+}
+
+fn limbs_from_bytes_be(bytes: &[u8; 32]) -> [u64; 4] {
+    let mut limbs = [0u64; 4];
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let start = 32 - 8 * (i + 1);
+        *limb = u64::from_be_bytes(bytes[start..start + 8].try_into().unwrap());
+    }
+    limbs
+}
+
+// Subtracts n once if the value (plus an overflow `carry` bit) is not below n
+fn subtract_n_if_needed(r: [u64; 4], carry: u64) -> [u64; 4] {
+    let mut d = [0u64; 4];
+    let mut borrow = 0;
+    for i in 0..4 {
+        (d[i], borrow) = sbb(r[i], N[i], borrow);
+    }
+    let mask = (carry | (borrow ^ 1)).wrapping_neg();
+    select(mask, &d, &r)
+}
+
+// t = lo + hi * 2^256 becomes lo + hi * C, which is the same mod n
+fn fold(t: [u64; 8]) -> [u64; 8] {
+    let mut r = [0u64; 8];
+    r[..4].copy_from_slice(&t[..4]);
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..3 {
+            let v = r[i + j] as u128 + t[i + 4] as u128 * C[j] as u128 + carry;
+            r[i + j] = v as u64;
+            carry = v >> 64;
+        }
+        for limb in r.iter_mut().skip(i + 3) {
+            let v = *limb as u128 + carry;
+            *limb = v as u64;
+            carry = v >> 64;
+        }
+    }
+    r
+}
+
+// Reduces a 512-bit product. Each fold shrinks the high part by 127 bits, so
+// after three of them the value is below 2^256 + 2^133 < 2n
+fn reduce_wide(t: [u64; 8]) -> [u64; 4] {
+    let t = fold(fold(fold(t)));
+    subtract_n_if_needed([t[0], t[1], t[2], t[3]], t[4])
+}
+
+impl Scalar {
+    pub fn zero() -> Self {
+        Scalar { limbs: [0; 4] }
+    }
+
+    pub fn one() -> Self {
+        Scalar {
+            limbs: [1, 0, 0, 0],
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        (self.limbs[0] | self.limbs[1] | self.limbs[2] | self.limbs[3]) == 0
+    }
+
+    /// Reads a 256-bit big-endian number and reduces it mod n.
+    pub fn from_bytes_be(bytes: &[u8; 32]) -> Self {
+        Scalar {
+            limbs: subtract_n_if_needed(limbs_from_bytes_be(bytes), 0),
+        }
+    }
+
+    /// Reads a 256-bit big-endian number, `None` when it is not below n. The
+    /// comparison goes through every limb, so secrets can be checked with it.
+    pub fn from_canonical_bytes_be(bytes: &[u8; 32]) -> Option<Self> {
+        let limbs = limbs_from_bytes_be(bytes);
+        let mut borrow = 0;
+        for (limb, n) in limbs.iter().zip(N) {
+            (_, borrow) = sbb(*limb, n, borrow);
+        }
+        (borrow == 1).then_some(Scalar { limbs })
+    }
+
+    pub fn to_bytes_be(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in self.limbs.iter().enumerate() {
+            let start = 32 - 8 * (i + 1);
+            bytes[start..start + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+
+    pub fn to_bytes_le(self) -> [u8; 32] {
+        let mut bytes = self.to_bytes_be();
+        bytes.reverse();
+        bytes
+    }
+
+    /// Reduces `number` mod n. Numbers wider than 256 bits are reduced with
+    /// `BigUint` first, so those should only be public values.
+    pub fn from_biguint(number: &BigUint) -> Self {
+        let bytes = number.to_bytes_be();
+        if bytes.len() > 32 {
+            let n = BigUint::from_bytes_be(&Scalar { limbs: N }.to_bytes_be());
+            return Self::from_biguint(&(number % n));
+        }
+
+        let mut buffer = [0u8; 32];
+        buffer[32 - bytes.len()..].copy_from_slice(&bytes);
+        Self::from_bytes_be(&buffer)
+    }
+
+    pub fn to_biguint(&self) -> BigUint {
+        BigUint::from_bytes_be(&self.to_bytes_be())
+    }
+
+    /// Multiplicative inverse, zero for zero.
+    pub fn invert(&self) -> Self {
+        self.pow_limbs(&N_MINUS_2)
+    }
+
+    /// True when the value is above (n - 1) / 2, i.e. it isn't a low-S value.
+    pub fn is_high(&self) -> bool {
+        let mut borrow = 0;
+        for (half, limb) in N_HALF.iter().zip(self.limbs) {
+            (_, borrow) = sbb(*half, limb, borrow);
+        }
+        borrow == 1
+    }
+
+    /// Returns `b` when `choice` is 1 and `a` when it is 0 without branching
+    /// on `choice`.
+    pub fn conditional_select(a: &Self, b: &Self, choice: u64) -> Self {
+        Scalar {
+            limbs: select(choice.wrapping_neg(), &b.limbs, &a.limbs),
+        }
+    }
+
+    // Square-and-multiply over a fixed 256-bit public exponent
+    fn pow_limbs(&self, exp: &[u64; 4]) -> Self {
+        let mut result = Scalar::one();
+        for limb in exp.iter().rev() {
+            for bit in (0..64).rev() {
+                result = result * result;
+                if (limb >> bit) & 1 == 1 {
+                    result = result * *self;
+                }
+            }
+        }
+        result
+    }
+}
+
+impl From<u64> for Scalar {
+    fn from(number: u64) -> Self {
+        Scalar {
+            limbs: [number, 0, 0, 0],
+        }
+    }
+}
+
+impl Debug for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Scalar({})", hex::encode(self.to_bytes_be()))
+    }
+}
+
+impl Add for Scalar {
+    type Output = Scalar;
+
+    fn add(self, rhs: Scalar) -> Scalar {
+        let mut r = [0u64; 4];
+        let mut carry = 0;
+        for (i, limb) in r.iter_mut().enumerate() {
+            (*limb, carry) = adc(self.limbs[i], rhs.limbs[i], carry);
+        }
+
+        Scalar {
+            limbs: subtract_n_if_needed(r, carry),
+        }
+    }
+}
+
+impl Sub for Scalar {
+    type Output = Scalar;
+
+    fn sub(self, rhs: Scalar) -> Scalar {
+        let mut r = [0u64; 4];
+        let mut borrow = 0;
+        for (i, limb) in r.iter_mut().enumerate() {
+            (*limb, borrow) = sbb(self.limbs[i], rhs.limbs[i], borrow);
+        }
+
+        // add n back when the subtraction wrapped around
+        let n = select(borrow.wrapping_neg(), &N, &[0; 4]);
+        let mut carry = 0;
+        for (limb, n) in r.iter_mut().zip(n) {
+            (*limb, carry) = adc(*limb, n, carry);
+        }
+
+        Scalar { limbs: r }
+    }
+}
+
+impl Neg for Scalar {
+    type Output = Scalar;
+
+    fn neg(self) -> Scalar {
+        Scalar::zero() - self
+    }
+}
+
+impl Mul for Scalar {
+    type Output = Scalar;
+
+    fn mul(self, rhs: Scalar) -> Scalar {
+        let mut t = [0u64; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let v = t[i + j] as u128 + self.limbs[i] as u128 * rhs.limbs[j] as u128 + carry;
+                t[i + j] = v as u64;
+                carry = v >> 64;
+            }
+            t[i + 4] = carry as u64;
+        }
+
+        Scalar {
+            limbs: reduce_wide(t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secp256k1::Secp256k1Point;
+
+    fn samples() -> Vec<BigUint> {
+        let n = Secp256k1Point::n();
+        vec![
+            BigUint::from(0u32),
+            BigUint::from(1u32),
+            BigUint::from(2u32),
+            BigUint::from(u64::MAX),
+            &n - 1u32,
+            &n - 2u32,
+            &n >> 1,
+            (&n >> 1) + 1u32,
+            BigUint::parse_bytes(
+                b"c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721",
+                16,
+            )
+            .unwrap(),
+            BigUint::parse_bytes(
+                b"8f8a277b3d8d4e2a9c5b1d6e0f3a7c4b2d1e9f8a7b6c5d4e3f2a1b0c9d8e7f60",
+                16,
+            )
+            .unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_from_bytes_reduces() {
+        let n = Secp256k1Point::n();
+        let cases = [
+            (n.clone(), BigUint::from(0u32)),
+            (&n + 1u32, BigUint::from(1u32)),
+            (
+                (BigUint::from(1u32) << 256) - 1u32,
+                (BigUint::from(1u32) << 256) - 1u32 - &n,
+            ),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(Scalar::from_biguint(&value).to_biguint(), expected);
+        }
+        assert_eq!(
+            Scalar::from_biguint(&((&n << 3) + 5u32)).to_biguint(),
+            BigUint::from(5u32)
+        );
+    }
+
+    #[test]
+    fn test_from_canonical_bytes() {
+        let n = Secp256k1Point::n();
+        let bytes = |value: &BigUint| -> [u8; 32] {
+            let mut bytes = [0u8; 32];
+            let value = value.to_bytes_be();
+            bytes[32 - value.len()..].copy_from_slice(&value);
+            bytes
+        };
+
+        for value in [BigUint::from(0u32), BigUint::from(1u32), &n - 1u32] {
+            assert_eq!(
+                Scalar::from_canonical_bytes_be(&bytes(&value)).map(|s| s.to_biguint()),
+                Some(value)
+            );
+        }
+        assert_eq!(Scalar::from_canonical_bytes_be(&bytes(&n)), None);
+        assert_eq!(Scalar::from_canonical_bytes_be(&bytes(&(&n + 1u32))), None);
+        assert_eq!(Scalar::from_canonical_bytes_be(&[0xff; 32]), None);
+    }
+
+    #[test]
+    fn test_arithmetic_matches_biguint() {
+        let n = Secp256k1Point::n();
+        for a in samples() {
+            for b in samples() {
+                let (x, y) = (Scalar::from_biguint(&a), Scalar::from_biguint(&b));
+
+                assert_eq!((x + y).to_biguint(), (&a + &b) % &n);
+                assert_eq!((x - y).to_biguint(), (&a + &n - &b) % &n);
+                assert_eq!((x * y).to_biguint(), (&a * &b) % &n);
+            }
+            assert_eq!((-Scalar::from_biguint(&a)).to_biguint(), (&n - &a) % &n);
+        }
+    }
+
+    #[test]
+    fn test_invert() {
+        let n = Secp256k1Point::n();
+        for a in samples().into_iter().filter(|a| *a != BigUint::from(0u32)) {
+            let x = Scalar::from_biguint(&a);
+
+            assert_eq!(x * x.invert(), Scalar::one());
+            assert_eq!(
+                x.invert().to_biguint(),
+                a.modpow(&Secp256k1Point::n_minus_2(), &n)
+            );
+        }
+        assert_eq!(Scalar::zero().invert(), Scalar::zero());
+    }
+
+    #[test]
+    fn test_is_high() {
+        let half = Secp256k1Point::n() >> 1;
+
+        assert!(!Scalar::from_biguint(&half).is_high());
+        assert!(Scalar::from_biguint(&(&half + 1u32)).is_high());
+        assert!(!Scalar::one().is_high());
+        assert!(!Scalar::zero().is_high());
+        assert!((-Scalar::one()).is_high());
+    }
+
+    #[test]
+    fn test_conditional_select() {
+        let (a, b) = (Scalar::from(3), Scalar::from(7));
+
+        assert_eq!(Scalar::conditional_select(&a, &b, 0), a);
+        assert_eq!(Scalar::conditional_select(&a, &b, 1), b);
+    }
+}
@@ -0,0 +1,503 @@
+#![allow(dead_code)]
+
+use crate::error::{Error, Result};
+use crate::hash::{sha256, tagged_hash, Sha256Hasher};
+use crate::point::Point;
+use crate::private_key::PrivateKey;
+use crate::scalar::Scalar;
+use crate::secp256k1::Secp256k1Point;
+use crate::secp256k1_field::Secp256k1Field;
+use num::Zero;
+use num_bigint::BigUint;
+
+// x coordinate and whether y is even, for points known not to be the
+// infinity
+fn coordinates(point: &Secp256k1Point) -> (Secp256k1Field, bool) {
+    match point {
+        Point::Coor { x, y, .. } => (*x, y.is_even()),
+        Point::Zero => panic!("the point at infinity has no coordinates"),
+    }
+}
+
+// int(hash(R || P || m)) mod n
+fn challenge(r: &[u8; 32], public_key: &XOnlyPublicKey, msg: &[u8]) -> BigUint {
+    let e = tagged_hash(
+        "BIP0340/challenge",
+        &[&r[..], &public_key.to_bytes()[..], msg].concat(),
+    );
+    BigUint::from_bytes_be(&e) % Secp256k1Point::n()
+}
+
+/// Public key identified only by its x coordinate, the point is the one
+/// with even y.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XOnlyPublicKey {
+    point: Secp256k1Point,
+}
+
+impl XOnlyPublicKey {
+    /// lift_x of BIP340: fails when x is not below p or not on the curve.
+    pub fn from_bytes(bytes: &[u8]) -> Result<XOnlyPublicKey> {
+        if bytes.len() != 32 {
+            return Err(Error::InvalidLength {
+                expected: 32,
+                found: bytes.len(),
+            });
+        }
+
+        let point = Secp256k1Point::from_sec(&[&[0x02], bytes].concat())?;
+        Ok(XOnlyPublicKey { point })
+    }
+
+    /// Drops the y coordinate of the point, so P and -P give the same key.
+    pub fn from_point(point: &Secp256k1Point) -> Result<XOnlyPublicKey> {
+        if *point == Point::Zero {
+            return Err(Error::PointAtInfinity);
+        }
+
+        let (x, is_even) = coordinates(point);
+        let point = if is_even {
+            point.clone()
+        } else {
+            -point.clone()
+        };
+        debug_assert_eq!(coordinates(&point).0, x);
+
+        Ok(XOnlyPublicKey { point })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        coordinates(&self.point).0.to_bytes_be()
+    }
+
+    pub fn to_point(&self) -> Secp256k1Point {
+        self.point.clone()
+    }
+
+    /// BIP341 output key Q = P + int(hash_TapTweak(P || merkle_root)) * G
+    /// of a taproot output with this internal key. `None` stands for an
+    /// output without script path.
+    pub fn tap_tweak(&self, merkle_root: Option<&[u8; 32]>) -> Result<XOnlyPublicKey> {
+        let mut hasher = Sha256Hasher::tagged("TapTweak");
+        hasher.update(&self.to_bytes());
+        if let Some(merkle_root) = merkle_root {
+            hasher.update(merkle_root);
+        }
+
+        let tweak = BigUint::from_bytes_be(&hasher.finalize());
+        if tweak >= Secp256k1Point::n() {
+            return Err(Error::InvalidTweak);
+        }
+
+        XOnlyPublicKey::from_point(&(self.point.clone() + Secp256k1Point::scale_generator(&tweak)))
+    }
+}
+
+/// Secret key together with its x-only public key.
+#[derive(Debug, Clone)]
+pub struct KeyPair {
+    secret: PrivateKey,
+    public_key: XOnlyPublicKey,
+    // P = d * G has an even y coordinate
+    has_even_y: bool,
+}
+
+impl KeyPair {
+    pub fn new(secret: &PrivateKey) -> KeyPair {
+        // d in [1, n - 1] never gives the point at infinity
+        let point = secret.public_key();
+        let has_even_y = coordinates(&point).1;
+        let point = if has_even_y { point } else { -point };
+        KeyPair {
+            secret: secret.clone(),
+            public_key: XOnlyPublicKey { point },
+            has_even_y,
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<KeyPair> {
+        Ok(Self::new(&PrivateKey::from_bytes(bytes)?))
+    }
+
+    pub fn public_key(&self) -> &XOnlyPublicKey {
+        &self.public_key
+    }
+
+    // The secret key of the even y point, d or n - d
+    fn even_secret(&self) -> Scalar {
+        let d = self.secret.scalar();
+        Scalar::conditional_select(&-d, &d, self.has_even_y as u64)
+    }
+}
+
+/// BIP340 signature: the x coordinate of R and the scalar s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub r: BigUint,
+    pub s: BigUint,
+}
+
+impl Signature {
+    pub fn to_bytes(&self) -> [u8; 64] {
+        self.try_to_bytes().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// r and s as 32 bytes each. Fails unless r is below p and s below n,
+    /// which `from_bytes` doesn't check.
+    pub fn try_to_bytes(&self) -> Result<[u8; 64]> {
+        if self.r >= Secp256k1Point::prime() || self.s >= Secp256k1Point::n() {
+            return Err(Error::SignatureOutOfRange);
+        }
+
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&Secp256k1Field::from_biguint(&self.r).to_bytes_be());
+        bytes[32..].copy_from_slice(&Scalar::from_biguint(&self.s).to_bytes_be());
+        Ok(bytes)
+    }
+
+    /// Only checks the length, out of range values fail in `verify`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Signature> {
+        if bytes.len() != 64 {
+            return Err(Error::InvalidLength {
+                expected: 64,
+                found: bytes.len(),
+            });
+        }
+
+        Ok(Signature {
+            r: BigUint::from_bytes_be(&bytes[..32]),
+            s: BigUint::from_bytes_be(&bytes[32..]),
+        })
+    }
+}
+
+/// Signs `msg` following BIP340. `aux_rand` is fresh randomness mixed into
+/// the nonce, which stays safe (just deterministic) if it is all zeros.
+pub fn sign(msg: &[u8], keypair: &KeyPair, aux_rand: &[u8; 32]) -> Result<Signature> {
+    let d = keypair.even_secret();
+    let public_key = keypair.public_key();
+
+    let mask = tagged_hash("BIP0340/aux", aux_rand);
+    let t: Vec<u8> = d
+        .to_bytes_be()
+        .iter()
+        .zip(mask.iter())
+        .map(|(a, b)| a ^ b)
+        .collect();
+
+    let rand = tagged_hash(
+        "BIP0340/nonce",
+        &[&t[..], &public_key.to_bytes()[..], msg].concat(),
+    );
+    let k = Scalar::from_bytes_be(&rand);
+    if k.is_zero() {
+        return Err(Error::InvalidNonce);
+    }
+
+    let (r, r_has_even_y) = coordinates(&Secp256k1Point::mul_generator(&k));
+    let k = Scalar::conditional_select(&-k, &k, r_has_even_y as u64);
+    let r = r.to_bytes_be();
+
+    let e = Scalar::from_biguint(&challenge(&r, public_key, msg));
+    let signature = Signature {
+        r: BigUint::from_bytes_be(&r),
+        s: (k + e * d).to_biguint(),
+    };
+
+    if !verify(msg, public_key, &signature) {
+        return Err(Error::SignatureNotVerified);
+    }
+    Ok(signature)
+}
+
+/// BIP340 verification: R = s * G - e * P must have an even y and x = r.
+pub fn verify(msg: &[u8], public_key: &XOnlyPublicKey, signature: &Signature) -> bool {
+    let n = Secp256k1Point::n();
+    let r: [u8; 32] = match signature.try_to_bytes() {
+        Ok(bytes) => bytes[..32].try_into().unwrap(),
+        Err(_) => return false,
+    };
+    let e = challenge(&r, public_key, msg);
+
+    let point = Secp256k1Point::multi_scale_glv(&[
+        (Secp256k1Point::generator(), signature.s.clone()),
+        (public_key.to_point(), (&n - e) % &n),
+    ]);
+
+    match point {
+        Point::Coor { x, y, .. } => y.is_even() && x.to_bytes_be() == r,
+        Point::Zero => false,
+    }
+}
+
+// a_2, ..., a_u of the batch, derived from a hash of all the inputs so they
+// can't be predicted by whoever picks the signatures
+fn batch_coefficients(batch: &[(&XOnlyPublicKey, &[u8], &Signature)]) -> Vec<BigUint> {
+    let mut hasher = Sha256Hasher::new();
+    for (public_key, msg, signature) in batch {
+        hasher.update(&public_key.to_bytes());
+        hasher.update(&(msg.len() as u64).to_be_bytes());
+        hasher.update(msg);
+        hasher.update(&signature.to_bytes());
+    }
+    let seed = hasher.finalize();
+
+    let n = Secp256k1Point::n();
+    (1..batch.len() as u64)
+        .map(|i| {
+            let mut counter = 0u64;
+            loop {
+                let hash = sha256(&[&seed[..], &i.to_be_bytes(), &counter.to_be_bytes()].concat());
+                let a = BigUint::from_bytes_be(&hash) % &n;
+                if !a.is_zero() {
+                    return a;
+                }
+                counter += 1;
+            }
+        })
+        .collect()
+}
+
+// sum(a_i * s_i) * G = sum(a_i * R_i) + sum(a_i * e_i * P_i) with a_1 = 1
+fn batch_equation_holds(batch: &[(&XOnlyPublicKey, &[u8], &Signature)]) -> bool {
+    let n = Secp256k1Point::n();
+    if batch
+        .iter()
+        .any(|(_, _, signature)| signature.try_to_bytes().is_err())
+    {
+        return false;
+    }
+
+    let coefficients = [BigUint::from(1u32)]
+        .into_iter()
+        .chain(batch_coefficients(batch))
+        .collect::<Vec<_>>();
+
+    let mut s_sum = BigUint::zero();
+    let mut terms = Vec::with_capacity(2 * batch.len() + 1);
+    for ((public_key, msg, signature), a) in batch.iter().zip(coefficients) {
+        let r: [u8; 32] = signature.to_bytes()[..32].try_into().unwrap();
+        let point = match XOnlyPublicKey::from_bytes(&r) {
+            Ok(key) => key.to_point(),
+            Err(_) => return false,
+        };
+        let e = challenge(&r, public_key, msg);
+
+        s_sum += &a * &signature.s;
+        terms.push((point, a.clone()));
+        terms.push((public_key.to_point(), (a * e) % &n));
+    }
+
+    // moving the left hand side over, every term must cancel out
+    let s_sum = s_sum % &n;
+    terms.push((Secp256k1Point::generator(), (&n - s_sum) % &n));
+    Secp256k1Point::multi_scale_glv(&terms) == Point::Zero
+}
+
+/// Verifies many signatures with a single multi-scalar multiplication
+/// (BIP340 batch verification). When the batch fails every signature is
+/// checked on its own and the error lists the positions of the bad ones.
+pub fn verify_batch(batch: &[(&XOnlyPublicKey, &[u8], &Signature)]) -> Result<()> {
+    if batch_equation_holds(batch) {
+        return Ok(());
+    }
+
+    let failed = batch
+        .iter()
+        .enumerate()
+        .filter(|(_, (public_key, msg, signature))| !verify(msg, public_key, signature))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignatures(failed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0340/test-vectors.csv
+    const TEST_VECTORS: &str = include_str!("../test_vectors/bip340.csv");
+
+    #[test]
+    fn test_bip340_vectors() {
+        for line in TEST_VECTORS.lines().skip(1) {
+            let fields: Vec<&str> = line.split(',').collect();
+            let (index, secret_key, public_key) = (fields[0], fields[1], fields[2]);
+            let aux_rand = hex::decode(fields[3]).unwrap();
+            let msg = hex::decode(fields[4]).unwrap();
+            let signature = hex::decode(fields[5]).unwrap();
+            let expected = fields[6] == "TRUE";
+
+            if !secret_key.is_empty() {
+                let keypair = KeyPair::from_bytes(&hex::decode(secret_key).unwrap()).unwrap();
+                assert_eq!(
+                    hex::encode_upper(keypair.public_key().to_bytes()),
+                    public_key,
+                    "public key of vector {index}"
+                );
+
+                let aux_rand: [u8; 32] = aux_rand.try_into().unwrap();
+                let produced = sign(&msg, &keypair, &aux_rand).unwrap();
+                assert_eq!(
+                    produced.to_bytes()[..],
+                    signature[..],
+                    "signature of vector {index}"
+                );
+            }
+
+            let result = XOnlyPublicKey::from_bytes(&hex::decode(public_key).unwrap())
+                .map(|key| verify(&msg, &key, &Signature::from_bytes(&signature).unwrap()));
+            assert_eq!(result.unwrap_or(false), expected, "vector {index}");
+        }
+    }
+
+    #[test]
+    fn test_x_only_public_key() {
+        let g = Secp256k1Point::generator();
+        let key = XOnlyPublicKey::from_point(&g).unwrap();
+
+        assert_eq!(key.to_point(), g);
+        assert_eq!(XOnlyPublicKey::from_point(&-g.clone()), Ok(key.clone()));
+        assert_eq!(XOnlyPublicKey::from_bytes(&key.to_bytes()), Ok(key));
+        assert_eq!(
+            XOnlyPublicKey::from_point(&Point::Zero),
+            Err(Error::PointAtInfinity)
+        );
+
+        assert_eq!(
+            XOnlyPublicKey::from_bytes(&[0u8; 33]),
+            Err(Error::InvalidLength {
+                expected: 32,
+                found: 33
+            })
+        );
+        assert_eq!(
+            XOnlyPublicKey::from_bytes(&Secp256k1Field::prime_bytes_be()),
+            Err(Error::FieldElementOutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_sign_odd_key() {
+        // d = n - 3 gives -3 * G, so the even y key is the one of d = 3
+        let odd = KeyPair::new(&PrivateKey::new(&(Secp256k1Point::n() - 3u32)).unwrap());
+        let even = KeyPair::new(&PrivateKey::new(&BigUint::from(3u32)).unwrap());
+        assert_eq!(odd.public_key(), even.public_key());
+
+        let aux_rand = [7u8; 32];
+        let signature = sign(b"message", &odd, &aux_rand).unwrap();
+        assert_eq!(sign(b"message", &even, &aux_rand), Ok(signature.clone()));
+        assert!(verify(b"message", odd.public_key(), &signature));
+        assert!(!verify(b"massage", odd.public_key(), &signature));
+
+        assert_eq!(
+            KeyPair::from_bytes(&[0u8; 32]).unwrap_err(),
+            Error::InvalidSecretKey
+        );
+        assert_eq!(
+            KeyPair::from_bytes(&Secp256k1Point::n().to_bytes_be()).unwrap_err(),
+            Error::InvalidSecretKey
+        );
+    }
+
+    fn batch<'a>(
+        keypairs: &'a [KeyPair],
+        messages: &'a [Vec<u8>],
+        signatures: &'a [Signature],
+    ) -> Vec<(&'a XOnlyPublicKey, &'a [u8], &'a Signature)> {
+        keypairs
+            .iter()
+            .zip(messages.iter())
+            .zip(signatures.iter())
+            .map(|((keypair, msg), signature)| (keypair.public_key(), &msg[..], signature))
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let keypairs = (1..=12u32)
+            .map(|i| KeyPair::new(&PrivateKey::new(&BigUint::from(i * 7919)).unwrap()))
+            .collect::<Vec<_>>();
+        let messages = (0..12u8).map(|i| vec![i; i as usize]).collect::<Vec<_>>();
+        let signatures = keypairs
+            .iter()
+            .zip(messages.iter())
+            .map(|(keypair, msg)| sign(msg, keypair, &[1u8; 32]).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            verify_batch(&batch(&keypairs, &messages, &signatures)),
+            Ok(())
+        );
+        assert_eq!(verify_batch(&[]), Ok(()));
+
+        let mut tampered = signatures.clone();
+        tampered[3].s = (&tampered[3].s + 1u32) % Secp256k1Point::n();
+        tampered[10].r = Secp256k1Point::prime();
+        assert_eq!(
+            verify_batch(&batch(&keypairs, &messages, &tampered)),
+            Err(Error::InvalidSignatures(vec![3, 10]))
+        );
+        assert_eq!(tampered[10].try_to_bytes(), Err(Error::SignatureOutOfRange));
+        tampered[10].r = &signatures[10].r + (BigUint::from(1u32) << 256);
+        assert_eq!(tampered[10].try_to_bytes(), Err(Error::SignatureOutOfRange));
+        assert!(!verify(
+            &messages[10],
+            keypairs[10].public_key(),
+            &tampered[10]
+        ));
+
+        // valid signatures under the wrong keys
+        let mut swapped = signatures.clone();
+        swapped.swap(0, 1);
+        assert_eq!(
+            verify_batch(&batch(&keypairs, &messages, &swapped)),
+            Err(Error::InvalidSignatures(vec![0, 1]))
+        );
+    }
+
+    #[test]
+    fn test_verify_batch_bip340_vectors() {
+        let vectors = TEST_VECTORS
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split(',').collect();
+                let public_key =
+                    XOnlyPublicKey::from_bytes(&hex::decode(fields[2]).unwrap()).ok()?;
+                let msg = hex::decode(fields[4]).unwrap();
+                let signature = Signature::from_bytes(&hex::decode(fields[5]).unwrap()).unwrap();
+                Some((public_key, msg, signature, fields[6] == "TRUE"))
+            })
+            .collect::<Vec<_>>();
+
+        let batch = vectors
+            .iter()
+            .map(|(public_key, msg, signature, _)| (public_key, &msg[..], signature))
+            .collect::<Vec<_>>();
+        let expected = vectors
+            .iter()
+            .enumerate()
+            .filter(|(_, vector)| !vector.3)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        assert!(!expected.is_empty());
+        assert_eq!(
+            verify_batch(&batch),
+            Err(Error::InvalidSignatures(expected))
+        );
+
+        let valid = batch
+            .iter()
+            .zip(vectors.iter())
+            .filter(|(_, vector)| vector.3)
+            .map(|(item, _)| *item)
+            .collect::<Vec<_>>();
+        assert_eq!(verify_batch(&valid), Ok(()));
+    }
+}
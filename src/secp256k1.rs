@@ -1,11 +1,50 @@
 #![allow(dead_code)]
 
-use crate::finite_field::FiniteField;
+use crate::encoding::{Decodable, DecodeLimits, Encodable};
+use crate::error::{Error, Result};
+use crate::jacobian::{batch_to_affine, JacobianPoint};
 use crate::point::Point;
+use crate::private_key::PrivateKey;
+use crate::projective::{montgomery_ladder, ProjectivePoint};
+use crate::scalar::Scalar;
+use crate::secp256k1_field::Secp256k1Field;
 
 use num_bigint::BigUint;
+use std::io::{self, Read, Write};
+use std::sync::OnceLock;
 
-pub type Secp256k1Point = Point;
+pub type Secp256k1Point = Point<Secp256k1Field>;
+
+// Bits of the scalar consumed by each row of the generator table
+const TABLE_WINDOW: usize = 4;
+
+// Row i holds [0, 1, ..., 15] * 16^i * G, so multiplying the generator only
+// takes one addition per 4-bit window of the scalar and no doublings.
+fn generator_table() -> &'static Vec<Vec<ProjectivePoint>> {
+    static TABLE: OnceLock<Vec<Vec<ProjectivePoint>>> = OnceLock::new();
+
+    TABLE.get_or_init(|| {
+        let row_len = 1 << TABLE_WINDOW;
+        let rows = 256 / TABLE_WINDOW;
+
+        let mut points = Vec::with_capacity(rows * row_len);
+        let mut base = JacobianPoint::from(Secp256k1Point::generator());
+        for _ in 0..rows {
+            let mut multiple = JacobianPoint::Zero;
+            for _ in 0..row_len {
+                points.push(multiple.clone());
+                multiple = multiple.add(&base);
+            }
+            // after the loop multiple = 16 * base
+            base = multiple;
+        }
+
+        batch_to_affine(&points)
+            .chunks(row_len)
+            .map(|row| row.iter().map(ProjectivePoint::from_affine).collect())
+            .collect()
+    })
+}
 
 impl Secp256k1Point {
     pub fn prime() -> BigUint {
@@ -20,57 +59,156 @@ impl Secp256k1Point {
         BigUint::from_bytes_be(&n)
     }
 
-    pub fn a() -> FiniteField {
-        FiniteField::from_bytes_be(&[0u8], &Self::prime().to_bytes_be())
+    pub fn a() -> Secp256k1Field {
+        Secp256k1Field::zero()
     }
 
-    pub fn b() -> FiniteField {
-        FiniteField::from_bytes_be(&[7u8], &Self::prime().to_bytes_be())
+    pub fn b() -> Secp256k1Field {
+        Secp256k1Field::from(7)
     }
 
-    pub fn generator() -> Point {
-        let gx = hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
-            .unwrap();
-        let gy = hex::decode("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")
-            .unwrap();
+    pub fn generator() -> Secp256k1Point {
+        static GENERATOR: OnceLock<Secp256k1Point> = OnceLock::new();
+
+        GENERATOR
+            .get_or_init(|| {
+                let gx =
+                    hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                        .unwrap();
+                let gy =
+                    hex::decode("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")
+                        .unwrap();
+
+                Secp256k1Point::from_bytes_be(&gx, &gy)
+            })
+            .clone()
+    }
+
+    /// k * G using the lazily built generator table. Every window does a
+    /// constant-time table lookup and a complete addition, so it is safe to
+    /// call with private keys and nonces.
+    pub fn scale_generator(k: &BigUint) -> Secp256k1Point {
+        Self::mul_generator(&Scalar::from_biguint(k))
+    }
+
+    /// `scale_generator` for a scalar already reduced mod n.
+    pub fn mul_generator(k: &Scalar) -> Secp256k1Point {
+        let k = k.to_bytes_le();
+        let mut result = ProjectivePoint::identity();
+
+        for (i, row) in generator_table().iter().enumerate() {
+            let byte = k[i / 2];
+            let digit = if i % 2 == 0 { byte & 0x0f } else { byte >> 4 };
+            result = result.add(&ProjectivePoint::lookup(row, digit));
+        }
+        result.to_affine()
+    }
 
-        Secp256k1Point::from_bytes_be(&gx, &gy)
+    /// Constant-time k * P for secret scalars, `scale` is faster but its
+    /// running time depends on k so it should only see public data.
+    pub fn scale_constant_time(&self, k: &BigUint) -> Secp256k1Point {
+        montgomery_ladder(self, &Scalar::from_biguint(k))
     }
 
-    pub fn compute_public_key(e: &BigUint) -> Point {
-        Secp256k1Point::generator().scale(e.clone())
+    pub fn compute_public_key(e: &PrivateKey) -> Secp256k1Point {
+        e.public_key()
     }
 
     pub fn n_minus_2() -> BigUint {
         Self::n() - BigUint::from(2u32)
     }
 
-    pub fn from_bytes_be(x: &[u8], y: &[u8]) -> Point {
-        let prime = hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F")
-            .unwrap();
+    pub fn from_bytes_be(x: &[u8], y: &[u8]) -> Secp256k1Point {
+        Self::try_from_bytes_be(x, y).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_bytes_be(x: &[u8], y: &[u8]) -> Result<Secp256k1Point> {
+        let x = Secp256k1Field::try_from_bytes_be(x)?;
+        let y = Secp256k1Field::try_from_bytes_be(y)?;
+
+        Point::try_new(&Self::a(), &Self::b(), &x, &y)
+    }
 
-        let x = FiniteField::from_bytes_be(&x, &prime);
-        let y = FiniteField::from_bytes_be(&y, &prime);
+    /// SEC1 encoding: 0x02/0x03 (even/odd y) followed by x when compressed
+    /// (33 bytes), 0x04 followed by x and y otherwise (65 bytes). The point at
+    /// infinity is the single byte 0x00.
+    pub fn to_sec(&self, compressed: bool) -> Vec<u8> {
+        match self {
+            Point::Coor { x, y, .. } => {
+                let mut sec = Vec::with_capacity(65);
+                if compressed {
+                    sec.push(if y.is_even() { 0x02 } else { 0x03 });
+                    sec.extend_from_slice(&x.to_bytes_be());
+                } else {
+                    sec.push(0x04);
+                    sec.extend_from_slice(&x.to_bytes_be());
+                    sec.extend_from_slice(&y.to_bytes_be());
+                }
+                sec
+            }
+            Point::Zero => vec![0x00],
+        }
+    }
 
-        let point = Point::Coor {
-            a: Self::a(),
-            b: Self::b(),
-            x: x.clone(),
-            y: y.clone(),
+    /// Parses a compressed or uncompressed SEC1 public key. Compressed keys
+    /// get y back from y^2 = x^3 + 7, picking the root with the parity of the
+    /// prefix.
+    pub fn from_sec(sec: &[u8]) -> Result<Secp256k1Point> {
+        let prefix = *sec.first().ok_or(Error::InvalidSecLength {
+            prefix: 0x00,
+            len: 0,
+        })?;
+        let expected_len = match prefix {
+            0x02 | 0x03 => 33,
+            0x04 => 65,
+            0x00 => return Err(Error::PointAtInfinity),
+            _ => return Err(Error::InvalidSecPrefix(prefix)),
         };
+        if sec.len() != expected_len {
+            return Err(Error::InvalidSecLength {
+                prefix,
+                len: sec.len(),
+            });
+        }
 
-        if !Point::is_on_curve(&point) {
-            panic!("({:?},{:?}) point is not in the curve", x, y);
+        if prefix == 0x04 {
+            return Self::try_from_bytes_be(&sec[1..33], &sec[33..]);
         }
 
-        point
+        let x = Secp256k1Field::try_from_bytes_be(&sec[1..])?;
+        let y_square = x * x * x + Self::b();
+        let y = y_square
+            .sqrt()
+            .ok_or(Error::InvalidSecX(BigUint::from_bytes_be(&sec[1..])))?;
+        let y = if y.is_even() == (prefix == 0x02) {
+            y
+        } else {
+            -y
+        };
+
+        Point::try_new(&Self::a(), &Self::b(), &x, &y)
+    }
+}
+
+/// Length-prefixed compressed SEC, as public keys appear in scripts.
+impl Encodable for Secp256k1Point {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+        self.to_sec(true).encode(writer)
+    }
+}
+
+/// Accepts both SEC forms, but not the point at infinity.
+impl Decodable for Secp256k1Point {
+    fn decode<R: Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<Self> {
+        Self::from_sec(&Vec::<u8>::decode(reader, limits)?)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use hex;
+    use crate::encoding::{deserialize, serialize};
+    use crate::error::DecodeError;
 
     #[test]
     fn test_bitcoin_generator_point() {
@@ -83,4 +221,225 @@ mod tests {
 
         assert_eq!(point.scale(BigUint::from_bytes_be(&n)), Point::Zero);
     }
+
+    #[test]
+    fn test_scale_generator() {
+        let g = Secp256k1Point::generator();
+        let scalars = [
+            BigUint::from(0u32),
+            BigUint::from(1u32),
+            BigUint::from(15u32),
+            BigUint::from(16u32),
+            BigUint::from(12345u32),
+            Secp256k1Point::n() - 1u32,
+            Secp256k1Point::n(),
+            Secp256k1Point::n() + 5u32,
+            BigUint::from_bytes_be(
+                &hex::decode("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721")
+                    .unwrap(),
+            ),
+        ];
+
+        for k in scalars {
+            let expected = g.clone().scale_double_and_add(k.clone());
+            assert_eq!(Secp256k1Point::scale_generator(&k), expected);
+            assert_eq!(g.scale_constant_time(&k), expected);
+            assert_eq!(g.clone().scale(k), expected);
+        }
+    }
+
+    #[test]
+    fn test_multi_scale() {
+        let g = Secp256k1Point::generator();
+        let k = BigUint::from_bytes_be(
+            &hex::decode("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721")
+                .unwrap(),
+        );
+
+        for count in [2, 12] {
+            let terms = (1..=count)
+                .map(|i| {
+                    let p = Secp256k1Point::scale_generator(&BigUint::from(i as u32 * 1000 + 7));
+                    let scalar = (k.clone() * BigUint::from(i as u32)) % Secp256k1Point::n();
+                    (p, scalar)
+                })
+                .collect::<Vec<_>>();
+
+            let expected = terms
+                .iter()
+                .fold(Point::Zero, |acc, (p, k)| acc + p.clone().scale(k.clone()));
+
+            assert_eq!(Point::multi_scale(&terms), expected);
+        }
+
+        // terms cancelling each other
+        let terms = [(g.clone(), BigUint::from(3u32)), (-g, BigUint::from(3u32))];
+        assert_eq!(Point::multi_scale(&terms), Point::Zero);
+    }
+
+    #[test]
+    fn test_sec() {
+        // Programming Bitcoin, chapter 4 exercises 1 and 2
+        let cases = [
+            (
+                "1388",
+                false,
+                "04ffe558e388852f0120e46af2d1b370f85854a8eb0841811ece0e3e03d282d57c315dc72890a4f10a1481c031b03b351b0dc79901ca18a00cf009dbdb157a1d10",
+            ),
+            (
+                "76e54a40efb620",
+                false,
+                "04027f3da1918455e03c46f659266a1bb5204e959db7364d2f473bdf8f0a13cc9dff87647fd023c13b4a4994f17691895806e1b40b57f4fd22581a4f46851f3b06",
+            ),
+            (
+                "0deadbeef12345",
+                false,
+                "04d90cd625ee87dd38656dd95cf79f65f60f7273b67d3096e68bd81e4f5342691f842efa762fd59961d0e99803c61edba8b3e3f7dc3a341836f97733aebf987121",
+            ),
+            (
+                "1389",
+                true,
+                "0357a4f368868a8a6d572991e484e664810ff14c05c0fa023275251151fe0e53d1",
+            ),
+            (
+                "7730c781f7ae53",
+                true,
+                "02933ec2d2b111b92737ec12f1c5d20f3233a0ad21cd8b36d0bca7a0cfa5cb8701",
+            ),
+            (
+                "0deadbeef54321",
+                true,
+                "0296be5b1292f6c856b3c5654e886fc13511462059089cdf9c479623bfcbe77690",
+            ),
+        ];
+
+        for (e, compressed, sec) in cases {
+            let e = PrivateKey::new(&BigUint::parse_bytes(e.as_bytes(), 16).unwrap()).unwrap();
+            let point = Secp256k1Point::compute_public_key(&e);
+            let sec = hex::decode(sec).unwrap();
+
+            assert_eq!(point.to_sec(compressed), sec);
+            assert_eq!(Secp256k1Point::from_sec(&sec), Ok(point.clone()));
+            assert_eq!(
+                Secp256k1Point::from_sec(&point.to_sec(!compressed)),
+                Ok(point)
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_sec_errors() {
+        let g = Secp256k1Point::generator();
+        let compressed = g.to_sec(true);
+        let uncompressed = g.to_sec(false);
+
+        assert_eq!(
+            Secp256k1Point::from_sec(&[]),
+            Err(Error::InvalidSecLength {
+                prefix: 0x00,
+                len: 0
+            })
+        );
+        assert_eq!(
+            Secp256k1Point::from_sec(&Point::Zero.to_sec(true)),
+            Err(Error::PointAtInfinity)
+        );
+
+        let mut bad_prefix = compressed.clone();
+        bad_prefix[0] = 0x05;
+        assert_eq!(
+            Secp256k1Point::from_sec(&bad_prefix),
+            Err(Error::InvalidSecPrefix(0x05))
+        );
+
+        assert_eq!(
+            Secp256k1Point::from_sec(&compressed[..32]),
+            Err(Error::InvalidSecLength {
+                prefix: 0x02,
+                len: 32
+            })
+        );
+        assert_eq!(
+            Secp256k1Point::from_sec(&uncompressed[..33]),
+            Err(Error::InvalidSecLength {
+                prefix: 0x04,
+                len: 33
+            })
+        );
+
+        // x = 5 gives y^2 = 132, which is not a square mod p
+        let mut no_y = vec![0x02];
+        no_y.extend_from_slice(&Secp256k1Field::from(5).to_bytes_be());
+        assert_eq!(
+            Secp256k1Point::from_sec(&no_y),
+            Err(Error::InvalidSecX(BigUint::from(5u32)))
+        );
+
+        let mut x_too_big = vec![0x03];
+        x_too_big.extend_from_slice(&Secp256k1Field::prime_bytes_be());
+        assert_eq!(
+            Secp256k1Point::from_sec(&x_too_big),
+            Err(Error::FieldElementOutOfRange)
+        );
+
+        let mut off_curve = uncompressed.clone();
+        off_curve[64] ^= 1;
+        assert!(matches!(
+            Secp256k1Point::from_sec(&off_curve),
+            Err(Error::PointNotOnCurve { .. })
+        ));
+    }
+
+    #[test]
+    fn test_encodable() {
+        let g = Secp256k1Point::generator();
+        let bytes = serialize(&g);
+
+        assert_eq!(bytes[0], 33);
+        assert_eq!(bytes[1..], g.to_sec(true));
+        assert_eq!(deserialize::<Secp256k1Point>(&bytes), Ok(g.clone()));
+        assert_eq!(
+            deserialize::<Secp256k1Point>(&serialize(&g.to_sec(false))),
+            Ok(g)
+        );
+        assert_eq!(
+            deserialize::<Secp256k1Point>(&serialize(&Point::Zero)),
+            Err(Error::PointAtInfinity)
+        );
+        assert_eq!(
+            deserialize::<Secp256k1Point>(&bytes[..20]),
+            Err(DecodeError::UnexpectedEnd.into())
+        );
+    }
+
+    #[test]
+    fn test_try_from_bytes_be_errors() {
+        let gx = hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+            .unwrap();
+        let gy = hex::decode("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")
+            .unwrap();
+
+        assert_eq!(
+            Secp256k1Point::try_from_bytes_be(&gx, &gy),
+            Ok(Secp256k1Point::generator())
+        );
+
+        assert_eq!(
+            Secp256k1Point::try_from_bytes_be(&gx, &gx),
+            Err(Error::PointNotOnCurve {
+                x: BigUint::from_bytes_be(&gx),
+                y: BigUint::from_bytes_be(&gx)
+            })
+        );
+
+        let prime = Secp256k1Point::prime().to_bytes_be();
+        assert_eq!(
+            Secp256k1Point::try_from_bytes_be(&prime, &gy),
+            Err(Error::FieldElementOutOfRange)
+        );
+        assert_eq!(
+            Secp256k1Point::try_from_bytes_be(&[1u8; 33], &gy),
+            Err(Error::FieldElementOutOfRange)
+        );
+    }
 }
@@ -0,0 +1,451 @@
+#![allow(dead_code)]
+
+use crate::error::{Error, Result};
+use crate::finite_field::Field;
+use num::Integer;
+use num_bigint::{BigInt, BigUint, ToBigInt};
+use std::fmt::{self, Debug};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// p = 2^256 - 2^32 - 977, stored as little-endian 64-bit limbs
+const P: [u64; 4] = [
+    0xFFFFFFFEFFFFFC2F,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+// 2^256 mod p = 2^32 + 977
+const R: u64 = 0x1000003D1;
+
+// p - 2, the exponent used for inversions (Fermat's little theorem)
+const P_MINUS_2: [u64; 4] = [
+    0xFFFFFFFEFFFFFC2D,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+// (p + 1) / 4, used for square roots since p = 3 mod 4
+const P_PLUS_1_DIV_4: [u64; 4] = [
+    0xFFFFFFFFBFFFFF0C,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0x3FFFFFFFFFFFFFFF,
+];
+
+// (p - 1) / 2, the exponent of Euler's criterion
+const P_MINUS_1_DIV_2: [u64; 4] = [
+    0xFFFFFFFF7FFFFE17,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0x7FFFFFFFFFFFFFFF,
+];
+
+/// Element of the secp256k1 base field stored in four 64-bit limbs. The value
+/// is always kept fully reduced, i.e. in the range [0, p).
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct Secp256k1Field {
+    limbs: [u64; 4],
+}
+
+pub(crate) fn adc(a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let t = a as u128 + b as u128 + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+pub(crate) fn sbb(a: u64, b: u64, borrow: u64) -> (u64, u64) {
+    let t = (a as u128).wrapping_sub(b as u128 + borrow as u128);
+    (t as u64, (t >> 127) as u64)
+}
+
+// Returns `a` when `mask` is all ones and `b` when it is all zeros
+pub(crate) fn select(mask: u64, a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    [
+        (a[0] & mask) | (b[0] & !mask),
+        (a[1] & mask) | (b[1] & !mask),
+        (a[2] & mask) | (b[2] & !mask),
+        (a[3] & mask) | (b[3] & !mask),
+    ]
+}
+
+// Subtracts p once if the value (plus an overflow `carry` bit) is not below p
+fn subtract_p_if_needed(r: [u64; 4], carry: u64) -> [u64; 4] {
+    let mut d = [0u64; 4];
+    let mut borrow = 0;
+    for i in 0..4 {
+        (d[i], borrow) = sbb(r[i], P[i], borrow);
+    }
+    let mask = (carry | (borrow ^ 1)).wrapping_neg();
+    select(mask, &d, &r)
+}
+
+// Reduces a 512-bit product using 2^256 = R (mod p)
+fn reduce_wide(t: [u64; 8]) -> [u64; 4] {
+    let mut r = [0u64; 4];
+    let mut carry = 0u128;
+    for i in 0..4 {
+        let v = t[i] as u128 + t[i + 4] as u128 * R as u128 + carry;
+        r[i] = v as u64;
+        carry = v >> 64;
+    }
+
+    // carry < 2^34, fold it back once more
+    let mut c = carry * R as u128;
+    for limb in r.iter_mut() {
+        let v = *limb as u128 + c;
+        *limb = v as u64;
+        c = v >> 64;
+    }
+
+    // c is either 0 or 1, and when it is set r is small enough to absorb R
+    let mut k = (c as u64) * R;
+    for limb in r.iter_mut() {
+        (*limb, k) = adc(*limb, k, 0);
+    }
+
+    subtract_p_if_needed(r, 0)
+}
+
+impl Secp256k1Field {
+    pub fn zero() -> Self {
+        Secp256k1Field { limbs: [0; 4] }
+    }
+
+    pub fn one() -> Self {
+        Secp256k1Field {
+            limbs: [1, 0, 0, 0],
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs == [0; 4]
+    }
+
+    pub fn from_bytes_be(bytes: &[u8]) -> Self {
+        if bytes.len() > 32 {
+            return Self::from_biguint(&BigUint::from_bytes_be(bytes));
+        }
+
+        let mut buffer = [0u8; 32];
+        buffer[32 - bytes.len()..].copy_from_slice(bytes);
+
+        let mut limbs = [0u64; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let start = 32 - 8 * (i + 1);
+            *limb = u64::from_be_bytes(buffer[start..start + 8].try_into().unwrap());
+        }
+
+        Secp256k1Field {
+            limbs: subtract_p_if_needed(limbs, 0),
+        }
+    }
+
+    /// Strict version of `from_bytes_be` for untrusted input: rejects
+    /// encodings longer than 32 bytes and values not smaller than p.
+    pub fn try_from_bytes_be(bytes: &[u8]) -> Result<Self> {
+        let element = Self::from_bytes_be(bytes);
+        if bytes.len() > 32 || element.to_biguint() != BigUint::from_bytes_be(bytes) {
+            return Err(Error::FieldElementOutOfRange);
+        }
+        Ok(element)
+    }
+
+    pub fn to_bytes_be(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in self.limbs.iter().enumerate() {
+            let start = 32 - 8 * (i + 1);
+            bytes[start..start + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_biguint(number: &BigUint) -> Self {
+        let prime = BigUint::from_bytes_be(&Secp256k1Field::prime_bytes_be());
+        Self::from_bytes_be(&(number % prime).to_bytes_be())
+    }
+
+    pub fn prime_bytes_be() -> [u8; 32] {
+        Secp256k1Field { limbs: P }.to_bytes_be()
+    }
+
+    pub fn square(&self) -> Self {
+        *self * *self
+    }
+
+    pub fn invert(&self) -> Self {
+        self.pow_limbs(&P_MINUS_2)
+    }
+
+    /// Returns `b` when `choice` is 1 and `a` when it is 0 without branching
+    /// on `choice`.
+    pub fn conditional_select(a: &Self, b: &Self, choice: u64) -> Self {
+        Secp256k1Field {
+            limbs: select(choice.wrapping_neg(), &b.limbs, &a.limbs),
+        }
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.limbs[0] & 1 == 0
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.is_zero() || self.pow_limbs(&P_MINUS_1_DIV_2) == Secp256k1Field::one()
+    }
+
+    /// Returns one of the square roots of the element, or `None` if it is not
+    /// a quadratic residue. The other root is its negation.
+    pub fn sqrt(&self) -> Option<Self> {
+        let root = self.pow_limbs(&P_PLUS_1_DIV_4);
+        if root.square() == *self {
+            Some(root)
+        } else {
+            None
+        }
+    }
+
+    // Square-and-multiply over a fixed 256-bit exponent
+    fn pow_limbs(&self, exp: &[u64; 4]) -> Self {
+        let mut result = Secp256k1Field::one();
+        for limb in exp.iter().rev() {
+            for bit in (0..64).rev() {
+                result = result.square();
+                if (limb >> bit) & 1 == 1 {
+                    result = result * *self;
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Field for Secp256k1Field {
+    fn pow(self, exp: &BigInt) -> Self {
+        let order = BigUint::from_bytes_be(&Secp256k1Field::prime_bytes_be()) - 1u32;
+        let exp = exp.mod_floor(&order.to_bigint().unwrap());
+        let exp = Secp256k1Field::from_bytes_be(&exp.to_biguint().unwrap().to_bytes_be());
+
+        self.pow_limbs(&exp.limbs)
+    }
+
+    fn scale(self, scalar: BigUint) -> Self {
+        self * Secp256k1Field::from_biguint(&scalar)
+    }
+
+    fn to_biguint(&self) -> BigUint {
+        BigUint::from_bytes_be(&self.to_bytes_be())
+    }
+
+    fn zero_element(&self) -> Self {
+        Secp256k1Field::zero()
+    }
+
+    fn one_element(&self) -> Self {
+        Secp256k1Field::one()
+    }
+}
+
+impl From<u64> for Secp256k1Field {
+    fn from(number: u64) -> Self {
+        Secp256k1Field {
+            limbs: [number, 0, 0, 0],
+        }
+    }
+}
+
+impl Debug for Secp256k1Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secp256k1Field({})", hex::encode(self.to_bytes_be()))
+    }
+}
+
+impl Add for Secp256k1Field {
+    type Output = Secp256k1Field;
+
+    fn add(self, rhs: Secp256k1Field) -> Secp256k1Field {
+        let mut r = [0u64; 4];
+        let mut carry = 0;
+        for (i, limb) in r.iter_mut().enumerate() {
+            (*limb, carry) = adc(self.limbs[i], rhs.limbs[i], carry);
+        }
+
+        Secp256k1Field {
+            limbs: subtract_p_if_needed(r, carry),
+        }
+    }
+}
+
+impl Sub for Secp256k1Field {
+    type Output = Secp256k1Field;
+
+    fn sub(self, rhs: Secp256k1Field) -> Secp256k1Field {
+        let mut r = [0u64; 4];
+        let mut borrow = 0;
+        for (i, limb) in r.iter_mut().enumerate() {
+            (*limb, borrow) = sbb(self.limbs[i], rhs.limbs[i], borrow);
+        }
+
+        // add p back when the subtraction wrapped around
+        let p = select(borrow.wrapping_neg(), &P, &[0; 4]);
+        let mut carry = 0;
+        for (limb, p) in r.iter_mut().zip(p) {
+            (*limb, carry) = adc(*limb, p, carry);
+        }
+
+        Secp256k1Field { limbs: r }
+    }
+}
+
+impl Neg for Secp256k1Field {
+    type Output = Secp256k1Field;
+
+    fn neg(self) -> Secp256k1Field {
+        Secp256k1Field::zero() - self
+    }
+}
+
+impl Mul for Secp256k1Field {
+    type Output = Secp256k1Field;
+
+    fn mul(self, rhs: Secp256k1Field) -> Secp256k1Field {
+        let mut t = [0u64; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let v = t[i + j] as u128 + self.limbs[i] as u128 * rhs.limbs[j] as u128 + carry;
+                t[i + j] = v as u64;
+                carry = v >> 64;
+            }
+            t[i + 4] = carry as u64;
+        }
+
+        Secp256k1Field {
+            limbs: reduce_wide(t),
+        }
+    }
+}
+
+impl Div for Secp256k1Field {
+    type Output = Secp256k1Field;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Secp256k1Field) -> Secp256k1Field {
+        self * rhs.invert()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finite_field::FiniteField;
+
+    fn prime() -> Vec<u8> {
+        hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F").unwrap()
+    }
+
+    fn samples() -> Vec<Vec<u8>> {
+        [
+            "00",
+            "01",
+            "07",
+            "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2E",
+            "00000000000000000000000000000000000000000000000000000001000003d0",
+        ]
+        .iter()
+        .map(|s| hex::decode(s).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        for bytes in samples() {
+            let a = Secp256k1Field::from_bytes_be(&bytes);
+            assert_eq!(a.to_biguint(), BigUint::from_bytes_be(&bytes));
+            assert_eq!(Secp256k1Field::from_bytes_be(&a.to_bytes_be()), a);
+        }
+
+        // values at or above p are reduced
+        assert_eq!(
+            Secp256k1Field::from_bytes_be(&prime()),
+            Secp256k1Field::zero()
+        );
+        assert_eq!(
+            Secp256k1Field::from_bytes_be(&[0xff; 32]),
+            Secp256k1Field::from(0x1000003D0)
+        );
+    }
+
+    #[test]
+    fn test_operations_match_finite_field() {
+        let prime = prime();
+
+        for lhs in samples() {
+            for rhs in samples() {
+                let a = Secp256k1Field::from_bytes_be(&lhs);
+                let b = Secp256k1Field::from_bytes_be(&rhs);
+                let fa = FiniteField::from_bytes_be(&lhs, &prime);
+                let fb = FiniteField::from_bytes_be(&rhs, &prime);
+
+                assert_eq!((a + b).to_biguint(), (fa.clone() + fb.clone()).number);
+                assert_eq!((a - b).to_biguint(), (fa.clone() - fb.clone()).number);
+                assert_eq!((a * b).to_biguint(), (fa.clone() * fb.clone()).number);
+
+                if !b.is_zero() {
+                    assert_eq!((a / b).to_biguint(), (fa / fb).number);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        let p_minus_1 = Secp256k1Field::zero() - Secp256k1Field::one();
+
+        assert_eq!(p_minus_1 + Secp256k1Field::one(), Secp256k1Field::zero());
+        assert_eq!(p_minus_1 * p_minus_1, Secp256k1Field::one());
+        assert_eq!(-p_minus_1, Secp256k1Field::one());
+        assert_eq!(-Secp256k1Field::zero(), Secp256k1Field::zero());
+
+        let a = Secp256k1Field::from(12345);
+        assert_eq!(a * a.invert(), Secp256k1Field::one());
+        assert_eq!(a.square().sqrt().map(|r| r == a || r == -a), Some(true));
+        assert_eq!(a.pow(&BigInt::from(-1)), a.invert());
+        assert_eq!(a.pow(&BigInt::from(2)), a.square());
+
+        let b = Secp256k1Field::from(54321);
+        assert_eq!(Secp256k1Field::conditional_select(&a, &b, 0), a);
+        assert_eq!(Secp256k1Field::conditional_select(&a, &b, 1), b);
+    }
+
+    #[test]
+    fn test_sqrt() {
+        let gx = Secp256k1Field::from_bytes_be(&samples()[3]);
+        let gy = Secp256k1Field::from_bytes_be(&samples()[4]);
+
+        let y_square = gx * gx * gx + Secp256k1Field::from(7);
+        let root = y_square.sqrt().unwrap();
+
+        assert!(root == gy || -root == gy);
+        assert_ne!(root.is_even(), (-root).is_even());
+        assert!(y_square.is_square());
+
+        assert_eq!(Secp256k1Field::zero().sqrt(), Some(Secp256k1Field::zero()));
+
+        // -1 is not a square when p = 3 mod 4
+        let minus_one = -Secp256k1Field::one();
+        assert!(!minus_one.is_square());
+        assert_eq!(minus_one.sqrt(), None);
+
+        for bytes in samples() {
+            let a = Secp256k1Field::from_bytes_be(&bytes);
+            let prime = prime();
+            let fa = FiniteField::from_bytes_be(&bytes, &prime);
+
+            assert_eq!(a.is_square(), fa.is_square());
+            assert_eq!(a.sqrt().is_some(), fa.sqrt().is_some());
+        }
+    }
+}
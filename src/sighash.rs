@@ -0,0 +1,457 @@
+#![allow(dead_code)]
+
+use crate::encoding::{serialize, CompactSize, Encodable};
+use crate::error::{Error, Result};
+use crate::hash::Sha256d;
+use crate::transaction::{Transaction, TxOut};
+use std::io::{self, Write};
+
+pub const SIGHASH_ALL: u32 = 0x01;
+pub const SIGHASH_NONE: u32 = 0x02;
+pub const SIGHASH_SINGLE: u32 = 0x03;
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+const OP_PUSHDATA4: u8 = 0x4e;
+const OP_CODESEPARATOR: u8 = 0xab;
+
+// What SIGHASH_SINGLE signs when the input has no output at its index
+const ONE: [u8; 32] = {
+    let mut one = [0u8; 32];
+    one[0] = 1;
+    one
+};
+
+// Opcode at `pc` and the position of the next one. When the script ends or
+// a push runs past its end, the error is where Bitcoin Core's GetOp stops:
+// after the opcode and the length bytes it could read
+fn next_op(script: &[u8], pc: usize) -> std::result::Result<(u8, usize), usize> {
+    let opcode = *script.get(pc).ok_or(pc)?;
+    let mut pc = pc + 1;
+
+    let size = match opcode {
+        0x01..=0x4b => opcode as usize,
+        OP_PUSHDATA1 => {
+            let size = *script.get(pc).ok_or(pc)? as usize;
+            pc += 1;
+            size
+        }
+        OP_PUSHDATA2 => {
+            let size = u16::from_le_bytes(script.get(pc..pc + 2).ok_or(pc)?.try_into().unwrap());
+            pc += 2;
+            size as usize
+        }
+        OP_PUSHDATA4 => {
+            let size = u32::from_le_bytes(script.get(pc..pc + 4).ok_or(pc)?.try_into().unwrap());
+            pc += 4;
+            size as usize
+        }
+        _ => 0,
+    };
+
+    if script.len() - pc < size {
+        return Err(pc);
+    }
+    Ok((opcode, pc + size))
+}
+
+/// Minimal push of `data`, the form signatures take in a script.
+pub fn push_data(data: &[u8]) -> Vec<u8> {
+    let mut script = match data.len() {
+        0..=0x4b => vec![data.len() as u8],
+        0x4c..=0xff => vec![OP_PUSHDATA1, data.len() as u8],
+        0x100..=0xffff => [&[OP_PUSHDATA2], &(data.len() as u16).to_le_bytes()[..]].concat(),
+        _ => [&[OP_PUSHDATA4], &(data.len() as u32).to_le_bytes()[..]].concat(),
+    };
+    script.extend_from_slice(data);
+    script
+}
+
+/// Bitcoin Core's `FindAndDelete`: removes every occurrence of `pattern`
+/// that starts at an opcode boundary. Legacy CHECKSIG applies it to the
+/// script code with the push of the signature before hashing.
+pub fn find_and_delete(script: &[u8], pattern: &[u8]) -> Vec<u8> {
+    if pattern.is_empty() {
+        return script.to_vec();
+    }
+
+    let mut result = Vec::with_capacity(script.len());
+    let mut found = false;
+    let (mut pc, mut copied) = (0, 0);
+    loop {
+        result.extend_from_slice(&script[copied..pc]);
+        while script[pc..].starts_with(pattern) {
+            pc += pattern.len();
+            found = true;
+        }
+        copied = pc;
+
+        match next_op(script, pc) {
+            Ok((_, next)) => pc = next,
+            Err(_) => break,
+        }
+    }
+
+    if !found {
+        return script.to_vec();
+    }
+    result.extend_from_slice(&script[copied..]);
+    result
+}
+
+// Bitcoin Core's SerializeScriptCode: the script code is signed without its
+// OP_CODESEPARATORs and copying stops where a push runs past the end of the
+// script. The length prefix still counts every byte but the separators.
+fn encode_script_code<W: Write + ?Sized>(script: &[u8], writer: &mut W) -> io::Result<usize> {
+    let mut separators = 0;
+    let mut pc = 0;
+    let end = loop {
+        match next_op(script, pc) {
+            Ok((opcode, next)) => {
+                separators += (opcode == OP_CODESEPARATOR) as usize;
+                pc = next;
+            }
+            Err(end) => break end,
+        }
+    };
+
+    let mut written = CompactSize((script.len() - separators) as u64).encode(writer)?;
+    let (mut pc, mut copied) = (0, 0);
+    while let Ok((opcode, next)) = next_op(script, pc) {
+        if opcode == OP_CODESEPARATOR {
+            writer.write_all(&script[copied..pc])?;
+            written += pc - copied;
+            copied = next;
+        }
+        pc = next;
+    }
+    writer.write_all(&script[copied..end])?;
+    Ok(written + end - copied)
+}
+
+fn serialize_into<T: Encodable + ?Sized>(value: &T, bytes: &mut Vec<u8>) {
+    value.encode(bytes).expect("writing to a Vec doesn't fail");
+}
+
+impl Transaction {
+    pub fn legacy_sighash(
+        &self,
+        input_index: usize,
+        script_code: &[u8],
+        hash_type: u32,
+    ) -> Sha256d {
+        self.try_legacy_sighash(input_index, script_code, hash_type)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Pre-segwit signature hash, the z that `Signature::sign` and
+    /// `Signature::verify` take for input `input_index`.
+    ///
+    /// `script_code` is the part of the script being executed after its last
+    /// executed OP_CODESEPARATOR, with the signature already removed by
+    /// `find_and_delete`. As in Bitcoin Core, SIGHASH_SINGLE without an
+    /// output at the input's index signs the value 1 instead of a hash.
+    pub fn try_legacy_sighash(
+        &self,
+        input_index: usize,
+        script_code: &[u8],
+        hash_type: u32,
+    ) -> Result<Sha256d> {
+        if input_index >= self.inputs.len() {
+            return Err(Error::InputIndexOutOfRange {
+                index: input_index,
+                inputs: self.inputs.len(),
+            });
+        }
+
+        // any other value of the low bits signs like SIGHASH_ALL
+        let base_type = hash_type & 0x1f;
+        if base_type == SIGHASH_SINGLE && input_index >= self.outputs.len() {
+            return Ok(Sha256d::from_byte_array(ONE));
+        }
+        let anyone_can_pay = hash_type & SIGHASH_ANYONECANPAY != 0;
+        let ignores_sequence = base_type == SIGHASH_NONE || base_type == SIGHASH_SINGLE;
+
+        let outputs = match base_type {
+            SIGHASH_NONE => Vec::new(),
+            // outputs before the input's index are blanked to value -1
+            SIGHASH_SINGLE => self.outputs[..=input_index]
+                .iter()
+                .enumerate()
+                .map(|(i, output)| {
+                    if i == input_index {
+                        output.clone()
+                    } else {
+                        TxOut {
+                            value: u64::MAX,
+                            script_pubkey: Vec::new(),
+                        }
+                    }
+                })
+                .collect(),
+            _ => self.outputs.clone(),
+        };
+
+        // the transaction with the script code as the signed input's
+        // script_sig and the other script_sigs empty
+        let mut bytes = serialize(&self.version);
+        let inputs: Vec<_> = self
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !anyone_can_pay || *i == input_index)
+            .collect();
+        serialize_into(&CompactSize(inputs.len() as u64), &mut bytes);
+        for (i, input) in inputs {
+            let signed = i == input_index;
+            serialize_into(&input.previous_output, &mut bytes);
+            if signed {
+                encode_script_code(script_code, &mut bytes).expect("writing to a Vec doesn't fail");
+            } else {
+                serialize_into(&CompactSize(0), &mut bytes);
+            }
+            let sequence = if signed || !ignores_sequence {
+                input.sequence
+            } else {
+                0
+            };
+            serialize_into(&sequence, &mut bytes);
+        }
+        serialize_into(&outputs, &mut bytes);
+        serialize_into(&self.locktime, &mut bytes);
+        serialize_into(&hash_type, &mut bytes);
+        Ok(Sha256d::hash(&bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::deserialize;
+    use crate::hash::Txid;
+    use crate::private_key::PrivateKey;
+    use crate::signature::Signature;
+    use crate::transaction::{OutPoint, TxIn};
+    use num_bigint::BigUint;
+
+    // Cases of Bitcoin Core's src/test/data/sighash.json. This copy still
+    // lacks the ones whose script has an OP_CODESEPARATOR, Core's file can
+    // replace it as is
+    const TEST_VECTORS: &str = include_str!("../test_vectors/sighash.json");
+
+    #[test]
+    fn test_sighash_vectors() {
+        let vectors: Vec<serde_json::Value> = serde_json::from_str(TEST_VECTORS).unwrap();
+
+        // entries with a single string are comments
+        for vector in vectors
+            .iter()
+            .filter(|vector| vector.as_array().unwrap().len() > 1)
+        {
+            let raw_tx = hex::decode(vector[0].as_str().unwrap()).unwrap();
+            let script = hex::decode(vector[1].as_str().unwrap()).unwrap();
+            let input_index = vector[2].as_u64().unwrap() as usize;
+            let hash_type = vector[3].as_i64().unwrap() as i32 as u32;
+            let expected = vector[4].as_str().unwrap();
+
+            let tx: Transaction = deserialize(&raw_tx).unwrap();
+            assert_eq!(
+                tx.legacy_sighash(input_index, &script, hash_type)
+                    .to_string(),
+                expected,
+                "{}",
+                vector
+            );
+        }
+    }
+
+    fn transaction(inputs: u32, outputs: u64) -> Transaction {
+        Transaction {
+            version: 1,
+            inputs: (0..inputs)
+                .map(|i| {
+                    let mut input = TxIn::new(OutPoint::new(Txid::hash(&i.to_le_bytes()), i));
+                    input.script_sig = vec![0x51];
+                    input.sequence = 0xfffffffe - i;
+                    input
+                })
+                .collect(),
+            outputs: (0..outputs)
+                .map(|i| TxOut {
+                    value: 1000 * (i + 1),
+                    script_pubkey: vec![0x76, 0xa9],
+                })
+                .collect(),
+            locktime: 0,
+        }
+    }
+
+    #[test]
+    fn test_sighash_single_bug() {
+        let tx = transaction(3, 1);
+        let one = "0000000000000000000000000000000000000000000000000000000000000001";
+
+        assert_eq!(tx.legacy_sighash(1, &[], SIGHASH_SINGLE).to_string(), one);
+        assert_eq!(
+            tx.legacy_sighash(2, &[0xac], SIGHASH_SINGLE | SIGHASH_ANYONECANPAY)
+                .to_string(),
+            one
+        );
+        assert_ne!(tx.legacy_sighash(0, &[], SIGHASH_SINGLE).to_string(), one);
+        assert_eq!(
+            tx.try_legacy_sighash(3, &[], SIGHASH_ALL),
+            Err(Error::InputIndexOutOfRange {
+                index: 3,
+                inputs: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_sighash_types() {
+        let tx = transaction(3, 3);
+        let script = [0x76, 0xa9, 0x88, 0xac];
+        let sighash = |tx: &Transaction, hash_type| tx.legacy_sighash(1, &script, hash_type);
+
+        // ALL commits to everything but the other script_sigs
+        let mut changed = tx.clone();
+        changed.inputs[0].script_sig = vec![0x52];
+        assert_eq!(sighash(&changed, SIGHASH_ALL), sighash(&tx, SIGHASH_ALL));
+        changed.inputs[0].sequence = 0;
+        assert_ne!(sighash(&changed, SIGHASH_ALL), sighash(&tx, SIGHASH_ALL));
+
+        // NONE ignores the outputs and the other sequences
+        let mut changed = tx.clone();
+        changed.outputs.pop();
+        changed.inputs[2].sequence = 7;
+        assert_eq!(sighash(&changed, SIGHASH_NONE), sighash(&tx, SIGHASH_NONE));
+        changed.inputs[1].sequence = 7;
+        assert_ne!(sighash(&changed, SIGHASH_NONE), sighash(&tx, SIGHASH_NONE));
+
+        // SINGLE only signs the output at the input's index
+        let mut changed = tx.clone();
+        changed.outputs[0].value = 1;
+        changed.outputs[2].value = 1;
+        assert_eq!(
+            sighash(&changed, SIGHASH_SINGLE),
+            sighash(&tx, SIGHASH_SINGLE)
+        );
+        changed.outputs[1].value = 1;
+        assert_ne!(
+            sighash(&changed, SIGHASH_SINGLE),
+            sighash(&tx, SIGHASH_SINGLE)
+        );
+
+        // ANYONECANPAY drops the other inputs
+        let mut changed = tx.clone();
+        changed.inputs.remove(2);
+        changed.inputs.remove(0);
+        let hash_type = SIGHASH_ALL | SIGHASH_ANYONECANPAY;
+        assert_eq!(
+            tx.legacy_sighash(1, &script, hash_type),
+            changed.legacy_sighash(0, &script, hash_type)
+        );
+        assert_ne!(
+            tx.legacy_sighash(1, &script, SIGHASH_ALL),
+            changed.legacy_sighash(0, &script, SIGHASH_ALL)
+        );
+
+        // the hash type itself is signed
+        assert_ne!(sighash(&tx, SIGHASH_ALL), sighash(&tx, 0x41));
+    }
+
+    #[test]
+    fn test_codeseparator() {
+        let tx = transaction(2, 2);
+        let sighash = |script: &str| {
+            tx.legacy_sighash(0, &hex::decode(script).unwrap(), SIGHASH_ALL)
+                .to_string()
+        };
+
+        // OP_CODESEPARATOR opcodes are removed wherever they are
+        assert_eq!(sighash("ab76a9ab88abac"), sighash("76a988ac"));
+        assert_eq!(sighash("abab"), sighash(""));
+        // but not an 0xab byte inside a push
+        assert_ne!(sighash("01ab76"), sighash("0176"));
+        let script_code = |script: &str| {
+            let mut bytes = Vec::new();
+            encode_script_code(&hex::decode(script).unwrap(), &mut bytes).unwrap();
+            hex::encode(bytes)
+        };
+        assert_eq!(script_code("01abab"), "0201ab");
+
+        // like Bitcoin Core, copying stops at a push that runs past the end
+        // of the script while the length still counts the bytes left out
+        assert_eq!(script_code("ab4c05ab"), "034c05");
+        assert_eq!(script_code("4d01"), "024d");
+        assert_eq!(script_code("ab4e0100"), "034e");
+        let mut preimage = hex::decode("01000000").unwrap();
+        preimage.push(2);
+        serialize_into(&tx.inputs[0].previous_output, &mut preimage);
+        preimage.extend(hex::decode("034c05").unwrap());
+        serialize_into(&tx.inputs[0].sequence, &mut preimage);
+        serialize_into(&tx.inputs[1].previous_output, &mut preimage);
+        preimage.push(0);
+        serialize_into(&tx.inputs[1].sequence, &mut preimage);
+        serialize_into(&tx.outputs, &mut preimage);
+        preimage.extend(hex::decode("0000000001000000").unwrap());
+        assert_eq!(sighash("ab4c05ab"), Sha256d::hash(&preimage).to_string());
+    }
+
+    #[test]
+    fn test_find_and_delete() {
+        let cases = [
+            ("0302ff03", "0302ff03", ""),
+            ("0302ff030302ff03", "0302ff03", ""),
+            // matches must start at an opcode boundary
+            ("0302ff030302ff03", "02", "0302ff030302ff03"),
+            ("0302ff030302ff03", "ff", "0302ff030302ff03"),
+            ("0302ff030302ff03", "03", "02ff0302ff03"),
+            ("02feed5169", "feed51", "02feed5169"),
+            ("02feed5169", "02feed51", "69"),
+            ("516902feed5169", "02feed51", "516969"),
+            ("0003feed", "03feed", "00"),
+            ("0003feed", "00", "03feed"),
+            ("51", "", "51"),
+        ];
+
+        for (script, pattern, expected) in cases {
+            assert_eq!(
+                hex::encode(find_and_delete(
+                    &hex::decode(script).unwrap(),
+                    &hex::decode(pattern).unwrap()
+                )),
+                expected,
+                "{script} without {pattern}"
+            );
+        }
+
+        assert_eq!(push_data(&[0xaa; 3]), [0x03, 0xaa, 0xaa, 0xaa]);
+        assert_eq!(push_data(&[0u8; 0x4c])[..2], [OP_PUSHDATA1, 0x4c]);
+        assert_eq!(push_data(&[0u8; 0x100])[..3], [OP_PUSHDATA2, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn test_sign_input() {
+        let key = PrivateKey::new(&BigUint::from(8675309u32)).unwrap();
+        let public_key = key.public_key();
+        let tx = transaction(2, 1);
+
+        // P2PK: <public key> OP_CHECKSIG
+        let script_code = [push_data(&public_key.to_sec(true)), vec![0xac]].concat();
+        let z = tx.legacy_sighash(1, &script_code, SIGHASH_ALL);
+        let signature = Signature::sign_deterministic(z.as_byte_array(), &key);
+        assert!(Signature::verify(
+            &signature,
+            z.as_byte_array(),
+            &public_key
+        ));
+
+        // a signature embedded in the script code is not signed
+        let signature_push = push_data(&[&signature.to_der()[..], &[SIGHASH_ALL as u8]].concat());
+        let with_signature = [&signature_push[..], &script_code[..]].concat();
+        let script_code = find_and_delete(&with_signature, &signature_push);
+        assert_eq!(tx.legacy_sighash(1, &script_code, SIGHASH_ALL), z);
+    }
+}
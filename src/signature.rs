@@ -1,52 +1,419 @@
 #![allow(dead_code)]
 
 use std::fmt::Debug;
+use std::io::{self, Read, Write};
 
+use crate::encoding::{Decodable, DecodeLimits, Encodable};
+use crate::error::{DerError, Error, Result};
+use crate::finite_field::Field;
 use crate::point::Point;
+use crate::private_key::PrivateKey;
+use crate::rfc6979::{bits_2_octets, generate_k_bytes, int_2_octets};
+use crate::scalar::Scalar;
 use crate::secp256k1::Secp256k1Point;
+use num::Zero;
 use num_bigint::BigUint;
 
 pub type PublicKey = Secp256k1Point; // P = e * G
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Signature {
     pub r: BigUint,
     pub s: BigUint,
 }
 
+// n / 2, the largest s of a low-S signature
+fn half_n() -> BigUint {
+    Secp256k1Point::n() >> 1
+}
+
+// Integer tag, length and minimal big-endian value of a non-zero 32-byte
+// number, with a 0x00 prefix when the high bit is set so the value isn't
+// read as negative
+fn der_integer(value: &[u8; 32]) -> Vec<u8> {
+    let start = value.iter().position(|byte| *byte != 0).unwrap_or(31);
+    let mut bytes = value[start..].to_vec();
+    if bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0x00);
+    }
+
+    let mut der = vec![0x02, bytes.len() as u8];
+    der.extend(bytes);
+    der
+}
+
+// BIP66 checks on one integer, `integer` starts at its tag byte
+fn check_der_integer(integer: &[u8], name: &'static str) -> std::result::Result<(), DerError> {
+    if integer[0] != 0x02 {
+        return Err(DerError::NotAnInteger(name));
+    }
+
+    let value = &integer[2..];
+    if value.is_empty() {
+        return Err(DerError::ZeroLengthInteger(name));
+    }
+    if value[0] & 0x80 != 0 {
+        return Err(DerError::NegativeInteger(name));
+    }
+    if value.len() > 1 && value[0] == 0x00 && value[1] & 0x80 == 0 {
+        return Err(DerError::ExcessPadding(name));
+    }
+    Ok(())
+}
+
+// Cursor used by `from_der_lax`, mirroring Bitcoin Core's
+// `ecdsa_signature_parse_der_lax` step by step
+struct LaxReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> LaxReader<'a> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn next(&mut self) -> std::result::Result<u8, DerError> {
+        let byte = *self.data.get(self.pos).ok_or(DerError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    // Sequence header, a long form length is skipped without looking at it
+    fn sequence(&mut self) -> std::result::Result<(), DerError> {
+        let tag = self.next()?;
+        if tag != 0x30 {
+            return Err(DerError::NotASequence(tag));
+        }
+
+        let len = self.next()? as usize;
+        if len & 0x80 != 0 {
+            let len_bytes = len - 0x80;
+            if len_bytes > self.remaining() {
+                return Err(DerError::UnexpectedEnd);
+            }
+            self.pos += len_bytes;
+        }
+        Ok(())
+    }
+
+    // Integer value with any length encoding, including long forms with
+    // leading zeros
+    fn integer(&mut self, name: &'static str) -> std::result::Result<&'a [u8], DerError> {
+        if self.next()? != 0x02 {
+            return Err(DerError::NotAnInteger(name));
+        }
+
+        let mut len = self.next()? as usize;
+        if len & 0x80 != 0 {
+            let mut len_bytes = len - 0x80;
+            if len_bytes > self.remaining() {
+                return Err(DerError::UnexpectedEnd);
+            }
+            while len_bytes > 0 && self.data[self.pos] == 0 {
+                self.pos += 1;
+                len_bytes -= 1;
+            }
+            if len_bytes >= std::mem::size_of::<usize>() {
+                return Err(DerError::WrongIntegerLength(name));
+            }
+            len = 0;
+            for _ in 0..len_bytes {
+                len = (len << 8) + self.next()? as usize;
+            }
+        }
+
+        if len > self.remaining() {
+            return Err(DerError::WrongIntegerLength(name));
+        }
+        let value = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(value)
+    }
+}
+
+// Lax integer value ignoring its leading zeros, `None` when it doesn't fit
+// in 32 bytes or is not below n
+fn lax_scalar(value: &[u8]) -> Option<BigUint> {
+    let start = value
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(value.len());
+    let value = &value[start..];
+    if value.len() > 32 {
+        return None;
+    }
+
+    let scalar = BigUint::from_bytes_be(value);
+    if scalar < Secp256k1Point::n() {
+        Some(scalar)
+    } else {
+        None
+    }
+}
+
 impl Signature {
-    pub fn sign(z: &[u8], e: &BigUint, k: &BigUint) -> Signature {
-        let z = BigUint::from_bytes_be(z);
-        let point = Secp256k1Point::generator().scale(k.clone());
+    // r and s as scalars, which only signatures with both in [1, n - 1] can
+    // be encoded as
+    fn scalars(&self) -> Result<(Scalar, Scalar)> {
+        let n = Secp256k1Point::n();
+        if self.r.is_zero() || self.r >= n || self.s.is_zero() || self.s >= n {
+            return Err(Error::SignatureOutOfRange);
+        }
+        Ok((Scalar::from_biguint(&self.r), Scalar::from_biguint(&self.s)))
+    }
 
-        if let Point::Coor { x, .. } = point {
-            let r = x.number;
-            let k_inv = k.modpow(&Secp256k1Point::n_minus_2(), &Secp256k1Point::n());
-            let s = ((z + r.clone() * e) * k_inv) % Secp256k1Point::n();
-            Signature { r, s }
-        } else {
-            panic!("it was not posible to generate the random point");
+    pub fn to_der(&self) -> Vec<u8> {
+        self.try_to_der().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// DER encoding: a sequence with r and s as minimal positive integers.
+    /// Fails unless both are in [1, n - 1].
+    pub fn try_to_der(&self) -> Result<Vec<u8>> {
+        let (r, s) = self.scalars()?;
+        let r = der_integer(&r.to_bytes_be());
+        let s = der_integer(&s.to_bytes_be());
+
+        let mut der = vec![0x30, (r.len() + s.len()) as u8];
+        der.extend(r);
+        der.extend(s);
+        Ok(der)
+    }
+
+    /// Parses a strict DER signature following the BIP66 rules of Bitcoin
+    /// Core's `IsValidSignatureEncoding`. `der` doesn't include the sighash
+    /// type byte that scripts append to signatures.
+    pub fn from_der(der: &[u8]) -> Result<Signature> {
+        let size = der.len();
+        if !(8..=72).contains(&size) {
+            return Err(DerError::InvalidLength(size).into());
+        }
+        if der[0] != 0x30 {
+            return Err(DerError::NotASequence(der[0]).into());
         }
+        if der[1] as usize != size - 2 {
+            return Err(DerError::WrongSequenceLength {
+                declared: der[1] as usize,
+                actual: size - 2,
+            }
+            .into());
+        }
+
+        let len_r = der[3] as usize;
+        if 5 + len_r >= size {
+            return Err(DerError::WrongIntegerLength("R").into());
+        }
+        let len_s = der[5 + len_r] as usize;
+        if len_r + len_s + 6 != size {
+            return Err(DerError::WrongIntegerLength("S").into());
+        }
+
+        check_der_integer(&der[2..4 + len_r], "R")?;
+        check_der_integer(&der[4 + len_r..], "S")?;
+
+        Ok(Signature {
+            r: BigUint::from_bytes_be(&der[4..4 + len_r]),
+            s: BigUint::from_bytes_be(&der[6 + len_r..]),
+        })
     }
 
-    pub fn sign_deterministic(z: &[u8], e: &BigUint) -> Signature {
-        //let k = generate_k(z, e, &Secp256k1Point::n());
-        let k = BigUint::from(12345u32);
-        let z = BigUint::from_bytes_be(z);
-        let point = Secp256k1Point::generator().scale(k.clone());
+    /// Parses the BER-like signatures found in the chain before BIP66, the
+    /// same way as Bitcoin Core's `ecdsa_signature_parse_der_lax`: long form
+    /// lengths, padding, negative integers and trailing bytes are accepted.
+    /// As in Core, an r or s that is not below n gives the signature (0, 0),
+    /// which never verifies, instead of an error.
+    pub fn from_der_lax(der: &[u8]) -> Result<Signature> {
+        let mut reader = LaxReader { data: der, pos: 0 };
+        reader.sequence()?;
+        let r = reader.integer("R")?;
+        let s = reader.integer("S")?;
 
-        if let Point::Coor { x, .. } = point {
-            let r = x.number;
-            let k_inv = k.modpow(&Secp256k1Point::n_minus_2(), &Secp256k1Point::n());
-            let s = ((z + r.clone() * e) * k_inv) % Secp256k1Point::n();
-            Signature { r, s }
+        match (lax_scalar(r), lax_scalar(s)) {
+            (Some(r), Some(s)) => Ok(Signature { r, s }),
+            _ => Ok(Signature {
+                r: BigUint::zero(),
+                s: BigUint::zero(),
+            }),
+        }
+    }
+
+    pub fn sign(z: &[u8], e: &PrivateKey, k: &Scalar) -> Signature {
+        Self::try_sign(z, e, k).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Signs z with the secret nonce k, which is a `Scalar` so that it only
+    /// goes through constant-time arithmetic.
+    pub fn try_sign(z: &[u8], e: &PrivateKey, k: &Scalar) -> Result<Signature> {
+        Self::try_sign_with_recovery_id(z, e, k).map(|(signature, _)| signature)
+    }
+
+    /// Signs with the nonce k derived from the private key and the message
+    /// hash as described in RFC 6979.
+    pub fn sign_deterministic(z: &[u8], e: &PrivateKey) -> Signature {
+        Self::try_sign_deterministic(z, e).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_sign_deterministic(z: &[u8], e: &PrivateKey) -> Result<Signature> {
+        Self::try_sign(z, e, &Self::deterministic_k(z, e))
+    }
+
+    /// Deterministic signature together with the recovery id that
+    /// `recover` needs to get the public key back.
+    pub fn sign_recoverable(z: &[u8], e: &PrivateKey) -> (Signature, u8) {
+        Self::try_sign_recoverable(z, e).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_sign_recoverable(z: &[u8], e: &PrivateKey) -> Result<(Signature, u8)> {
+        Self::try_sign_with_recovery_id(z, e, &Self::deterministic_k(z, e))
+    }
+
+    // Never a BigUint, whose length and arithmetic depend on the value
+    fn deterministic_k(z: &[u8], e: &PrivateKey) -> Scalar {
+        let n: [u8; 32] = int_2_octets(Secp256k1Point::n());
+        let z_octets: [u8; 32] = bits_2_octets(z, &n);
+
+        Scalar::from_bytes_be(&generate_k_bytes::<32, 32>(&z_octets, &e.to_bytes(), &n))
+    }
+
+    // The recovery id records the parity of R's y coordinate (bit 0) and
+    // whether R's x coordinate was reduced mod n to get r (bit 1)
+    fn try_sign_with_recovery_id(z: &[u8], e: &PrivateKey, k: &Scalar) -> Result<(Signature, u8)> {
+        let n = Secp256k1Point::n();
+        let z = Scalar::from_biguint(&BigUint::from_bytes_be(z));
+        let point = Secp256k1Point::mul_generator(k);
+
+        if let Point::Coor { x, y, .. } = point {
+            let x = x.to_biguint();
+            let mut recovery_id = (!y.is_even() as u8) | (((x >= n) as u8) << 1);
+
+            // k and the private key only go through constant-time scalar
+            // arithmetic, r and s are public
+            let r = Scalar::from_biguint(&x);
+            let mut s = (z + r * e.scalar()) * k.invert();
+            if r.is_zero() || s.is_zero() {
+                return Err(Error::InvalidNonce);
+            }
+
+            // n - s is the signature of -R, which has the other y parity
+            if s.is_high() {
+                s = -s;
+                recovery_id ^= 1;
+            }
+            let signature = Signature {
+                r: r.to_biguint(),
+                s: s.to_biguint(),
+            };
+            Ok((signature, recovery_id))
         } else {
-            panic!("it was not posible to generate the random point");
+            Err(Error::InvalidNonce)
+        }
+    }
+
+    pub fn recover(&self, z: &[u8], recovery_id: u8) -> PublicKey {
+        self.try_recover(z, recovery_id)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Public key that produced the signature of z: rebuilds R from r and
+    /// the recovery id, then P = r^-1 * (s * R - z * G).
+    pub fn try_recover(&self, z: &[u8], recovery_id: u8) -> Result<PublicKey> {
+        if recovery_id > 3 {
+            return Err(Error::InvalidRecoveryId(recovery_id));
+        }
+
+        let n = Secp256k1Point::n();
+        if self.r.is_zero() || self.r >= n || self.s.is_zero() || self.s >= n {
+            return Err(Error::RecoveryFailed);
+        }
+
+        let mut x = self.r.clone();
+        if recovery_id & 2 != 0 {
+            x += &n;
+        }
+        if x >= Secp256k1Point::prime() {
+            return Err(Error::RecoveryFailed);
+        }
+
+        let mut sec = vec![0x02 | (recovery_id & 1)];
+        sec.extend_from_slice(&int_2_octets::<32>(x));
+        let point = Secp256k1Point::from_sec(&sec).map_err(|_| Error::RecoveryFailed)?;
+
+        let r_inv = self.r.modpow(&Secp256k1Point::n_minus_2(), &n);
+        let z = BigUint::from_bytes_be(z) % &n;
+        let u = ((&n - z) * &r_inv) % &n;
+        let v = (&self.s * r_inv) % &n;
+
+        let public_key =
+            Secp256k1Point::multi_scale_glv(&[(Secp256k1Point::generator(), u), (point, v)]);
+        if public_key == Point::Zero {
+            return Err(Error::RecoveryFailed);
+        }
+        Ok(public_key)
+    }
+
+    /// 65-byte compact encoding used by `signmessage`: a header byte
+    /// 27 + recovery id (+ 4 when the public key is compressed), then r and s,
+    /// which must be in [1, n - 1].
+    pub fn to_compact(&self, recovery_id: u8, compressed: bool) -> Result<[u8; 65]> {
+        if recovery_id > 3 {
+            return Err(Error::InvalidRecoveryId(recovery_id));
         }
+
+        let (r, s) = self.scalars()?;
+
+        let mut compact = [0u8; 65];
+        compact[0] = 27 + recovery_id + if compressed { 4 } else { 0 };
+        compact[1..33].copy_from_slice(&r.to_bytes_be());
+        compact[33..].copy_from_slice(&s.to_bytes_be());
+        Ok(compact)
+    }
+
+    /// Parses a compact signature into the signature, the recovery id and
+    /// whether the public key is compressed.
+    pub fn from_compact(compact: &[u8]) -> Result<(Signature, u8, bool)> {
+        if compact.len() != 65 {
+            return Err(Error::InvalidCompactLength(compact.len()));
+        }
+
+        let header = compact[0];
+        if !(27..=34).contains(&header) {
+            return Err(Error::InvalidCompactHeader(header));
+        }
+
+        let signature = Signature {
+            r: BigUint::from_bytes_be(&compact[1..33]),
+            s: BigUint::from_bytes_be(&compact[33..]),
+        };
+        Ok((signature, (header - 27) & 3, header >= 31))
+    }
+
+    /// Both (r, s) and (r, n - s) are valid signatures of the same message.
+    /// BIP62 and BIP146 only relay the one with s <= n / 2.
+    pub fn is_low_s(&self) -> bool {
+        self.s <= half_n()
+    }
+
+    /// Replaces a high s by n - s, which keeps the signature valid.
+    pub fn normalize_s(&mut self) {
+        if !self.is_low_s() {
+            self.s = Secp256k1Point::n() - &self.s;
+        }
+    }
+
+    /// Same as `verify` but rejecting high-S signatures, like Bitcoin's
+    /// standardness rules (SCRIPT_VERIFY_LOW_S).
+    pub fn verify_low_s(signature: &Signature, z: &[u8], public_key: &PublicKey) -> bool {
+        signature.is_low_s() && Self::verify(signature, z, public_key)
     }
 
+    /// ECDSA verification. r and s must be in [1, n - 1], otherwise (r, s + n)
+    /// would verify just like (r, s).
     pub fn verify(signature: &Signature, z: &[u8], public_key: &PublicKey) -> bool {
         let n = Secp256k1Point::n();
+        let in_range = |v: &BigUint| !v.is_zero() && *v < n;
+        if !in_range(&signature.r) || !in_range(&signature.s) {
+            return false;
+        }
 
         let s_inv = signature
             .s
@@ -59,22 +426,41 @@ impl Signature {
         let u = (z * s_inv.clone()) % n.clone();
         let v = (r * s_inv) % n.clone();
 
-        let generator = Secp256k1Point::generator();
-        let point = generator.scale(u) + public_key.clone().scale(v);
+        let point = Secp256k1Point::multi_scale_glv(&[
+            (Secp256k1Point::generator(), u),
+            (public_key.clone(), v),
+        ]);
 
         if let Point::Coor { x, .. } = point {
-            x.number == *r
+            x.to_biguint() % n == *r
         } else {
             false
         }
     }
 }
 
+/// Length-prefixed DER, without a sighash type.
+impl Encodable for Signature {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+        let der = self
+            .try_to_der()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        der.encode(writer)
+    }
+}
+
+/// Only strict DER is accepted, see `from_der`.
+impl Decodable for Signature {
+    fn decode<R: Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<Self> {
+        Self::from_der(&Vec::<u8>::decode(reader, limits)?)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::hash::sha256_double;
-    use hex;
+    use crate::encoding::{deserialize, serialize};
+    use crate::hash::{sha256, sha256_double};
 
     #[test]
     fn test_verification_true() {
@@ -220,9 +606,9 @@ mod tests {
 
     #[test]
     fn test_sign_book_1() {
-        let e = BigUint::from_bytes_be(&sha256_double("my secret"));
-        let z = sha256_double("my message");
-        let k = BigUint::from(1234567890u32);
+        let e = PrivateKey::from_bytes(&sha256_double(b"my secret")).unwrap();
+        let z = sha256_double(b"my message");
+        let k = Scalar::from(1234567890);
 
         let signature = Signature::sign(&z, &e, &k);
 
@@ -230,11 +616,11 @@ mod tests {
 
         if let Point::Coor { x, y, .. } = public_key {
             assert_eq!(
-                hex::encode(x.number.to_bytes_be()),
+                hex::encode(x.to_bytes_be()),
                 "028d003eab2e428d11983f3e97c3fa0addf3b42740df0d211795ffb3be2f6c52"
             );
             assert_eq!(
-                hex::encode(y.number.to_bytes_be()),
+                hex::encode(y.to_bytes_be()),
                 "0ae987b9ec6ea159c78cb2a937ed89096fb218d9e7594f02b547526d8cd309e2"
             );
         }
@@ -243,32 +629,33 @@ mod tests {
             hex::encode(signature.r.to_bytes_be()),
             "2b698a0f0a4041b77e63488ad48c23e8e8838dd1fb7520408b121697b782ef22"
         );
+        // n - s of the book's bb14e602...8cb9, signing only produces low-S values
         assert_eq!(
             hex::encode(signature.s.to_bytes_be()),
-            "bb14e602ef9e3f872e25fad328466b34e6734b7a0fcd58b1eb635447ffae8cb9"
+            "44eb19fd1061c078d1da052cd7b994c9d43b916c9f7b4789d46f0a44d087b488"
         );
     }
 
     #[test]
     fn test_sign_book_2() {
-        let e = BigUint::from(12345u32);
+        let e = PrivateKey::new(&BigUint::from(12345u32)).unwrap();
 
         let public_key = Secp256k1Point::compute_public_key(&e);
 
         if let Point::Coor { x, y, .. } = public_key {
             assert_eq!(
-                hex::encode(x.number.to_bytes_be()),
+                hex::encode(x.to_bytes_be()),
                 "f01d6b9018ab421dd410404cb869072065522bf85734008f105cf385a023a80f"
             );
             assert_eq!(
-                hex::encode(y.number.to_bytes_be()),
+                hex::encode(y.to_bytes_be()),
                 "0eba29d0f0c5408ed681984dc525982abefccd9f7ff01dd26da4999cf3f6a295"
             );
         }
 
-        let z = sha256_double("Programming Bitcoin!");
+        let z = sha256_double(b"Programming Bitcoin!");
 
-        let k = BigUint::from(1234567890u32);
+        let k = Scalar::from(1234567890);
 
         let signature = Signature::sign(&z, &e, &k);
 
@@ -287,10 +674,618 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_try_sign_invalid_nonce() {
+        let e = PrivateKey::new(&BigUint::from(12345u32)).unwrap();
+        let z = sha256_double(b"Programming Bitcoin!");
+
+        assert_eq!(
+            Signature::try_sign(&z, &e, &Scalar::zero()).unwrap_err(),
+            Error::InvalidNonce
+        );
+        // n reduces to zero
+        let n = Scalar::from_bytes_be(&int_2_octets(Secp256k1Point::n()));
+        assert_eq!(
+            Signature::try_sign(&z, &e, &n).unwrap_err(),
+            Error::InvalidNonce
+        );
+        assert!(Signature::try_sign(&z, &e, &Scalar::from(1234567890)).is_ok());
+    }
+
+    #[test]
+    fn test_to_der() {
+        // Programming Bitcoin, chapter 4 exercise 3
+        let signature = Signature {
+            r: BigUint::from_bytes_be(
+                &hex::decode("37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6")
+                    .unwrap(),
+            ),
+            s: BigUint::from_bytes_be(
+                &hex::decode("8ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec")
+                    .unwrap(),
+            ),
+        };
+        let der = hex::decode(
+            "3045022037206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6022100\
+             8ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec",
+        )
+        .unwrap();
+
+        assert_eq!(signature.to_der(), der);
+        assert_eq!(Signature::from_der(&der), Ok(signature.clone()));
+        assert_eq!(Signature::from_der_lax(&der), Ok(signature));
+
+        let small = Signature {
+            r: BigUint::from(4u32),
+            s: BigUint::from(4u32),
+        };
+        assert_eq!(small.to_der(), hex::decode("3006020104020104").unwrap());
+
+        // values the length bytes can't describe are rejected, not encoded
+        let n = Secp256k1Point::n();
+        for (r, s) in [
+            (BigUint::zero(), BigUint::from(4u32)),
+            (BigUint::from(4u32), n.clone()),
+            (BigUint::from(1u32) << 2040, BigUint::from(4u32)),
+        ] {
+            let signature = Signature { r, s };
+            assert_eq!(signature.try_to_der(), Err(Error::SignatureOutOfRange));
+            assert!(signature.encode(&mut Vec::new()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_encodable() {
+        let signature = Signature::sign_deterministic(&sha256(b"encode"), &PrivateKey::random());
+        let bytes = serialize(&signature);
+
+        assert_eq!(bytes[0] as usize, bytes.len() - 1);
+        assert_eq!(bytes[1..], signature.to_der());
+        assert_eq!(deserialize::<Signature>(&bytes), Ok(signature));
+
+        // a valid BER signature that is not strict DER: r padded with a zero
+        let padded = hex::decode("300702020004020104").unwrap();
+        assert!(Signature::from_der_lax(&padded).is_ok());
+        assert!(matches!(
+            deserialize::<Signature>(&serialize(&padded)),
+            Err(Error::InvalidDer(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_der_bip66() {
+        // The "... is incorrectly encoded for DERSIG" cases of Bitcoin Core's
+        // src/test/data/script_tests.json: the signature push as written
+        // there, then the strict error and the lax parse of the signature
+        // without its last byte, the sighash type
+        let lax_signature = |lax: std::result::Result<(String, String), DerError>| {
+            lax.map(|(r, s)| Signature {
+                r: BigUint::from_bytes_be(&hex::decode(r).unwrap()),
+                s: BigUint::from_bytes_be(&hex::decode(s).unwrap()),
+            })
+            .map_err(Error::InvalidDer)
+        };
+        let r = "77".repeat(16);
+        let core_cases = [
+            (
+                "0x4a 0x0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+                DerError::InvalidLength(73),
+                Err(DerError::NotASequence(0x00)),
+            ),
+            (
+                "0x25 0x30220220000000000000000000000000000000000000000000000000000000000000000000",
+                DerError::WrongIntegerLength("R"),
+                Err(DerError::UnexpectedEnd),
+            ),
+            (
+                "0x27 0x3024021077777777777777777777777777777777020a7777777777777777777777777777777777",
+                DerError::WrongIntegerLength("S"),
+                Ok((r.clone(), "77".repeat(10))),
+            ),
+            (
+                "0x27 0x302403107777777777777777777777777777777702107777777777777777777777777777777701",
+                DerError::NotAnInteger("R"),
+                Err(DerError::NotAnInteger("R")),
+            ),
+            (
+                "0x27 0x302402107777777777777777777777777777777703107777777777777777777777777777777701",
+                DerError::NotAnInteger("S"),
+                Err(DerError::NotAnInteger("S")),
+            ),
+            (
+                "0x17 0x3014020002107777777777777777777777777777777701",
+                DerError::ZeroLengthInteger("R"),
+                Ok((String::new(), r.clone())),
+            ),
+            (
+                "0x17 0x3014021077777777777777777777777777777777020001",
+                DerError::ZeroLengthInteger("S"),
+                Ok((r.clone(), String::new())),
+            ),
+            (
+                "0x27 0x302402107777777777777777777777777777777702108777777777777777777777777777777701",
+                DerError::NegativeInteger("S"),
+                Ok((r.clone(), "87".to_owned() + &"77".repeat(15))),
+            ),
+        ];
+
+        for (push, strict, lax) in core_cases {
+            let (size, signature) = push.split_once(' ').unwrap();
+            let signature = hex::decode(&signature[2..]).unwrap();
+            assert_eq!(
+                signature.len(),
+                usize::from_str_radix(&size[2..], 16).unwrap()
+            );
+
+            let der = &signature[..signature.len() - 1];
+            assert_eq!(Signature::from_der(der), Err(Error::InvalidDer(strict)));
+            assert_eq!(Signature::from_der_lax(der), lax_signature(lax), "{push}");
+        }
+
+        // Hand-built encodings for the rules the cases above leave out, with
+        // 16-byte filler values for r and s. Like Core's lax parser, ours
+        // doesn't check the sequence length, only its tag
+        let high = "87".repeat(16);
+        let cases = [
+            (
+                format!("30240210{high}0210{r}"),
+                DerError::NegativeInteger("R"),
+                Ok((high.clone(), r.clone())),
+            ),
+            (
+                format!("3025021100{r}0210{r}"),
+                DerError::ExcessPadding("R"),
+                Ok((r.clone(), r.clone())),
+            ),
+            (
+                format!("30250210{r}021100{r}"),
+                DerError::ExcessPadding("S"),
+                Ok((r.clone(), r.clone())),
+            ),
+            (
+                format!("31240210{r}0210{r}"),
+                DerError::NotASequence(0x31),
+                Err(DerError::NotASequence(0x31)),
+            ),
+            (
+                format!("30250210{r}0210{r}"),
+                DerError::WrongSequenceLength {
+                    declared: 0x25,
+                    actual: 0x24,
+                },
+                Ok((r.clone(), r.clone())),
+            ),
+        ];
+
+        for (der, strict, lax) in cases {
+            let der = hex::decode(&der).unwrap();
+            assert_eq!(Signature::from_der(&der), Err(Error::InvalidDer(strict)));
+            assert_eq!(Signature::from_der_lax(&der), lax_signature(lax));
+        }
+
+        // a 0x00 byte is only allowed in front of a high bit
+        let der = format!("3025021100{}0210{r}", "87".repeat(16));
+        assert!(Signature::from_der(&hex::decode(der).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_from_der_wycheproof() {
+        // ecdsa_secp256k1_sha256_bitcoin_test.json, test ids in the comments
+        let der = "3045022100813ef79ccefa9a56f7ba805f0e478584fe5f0dd5f567bc09b5123ccbc9832365\
+                   02206ff18a52dcc0336f7af62400a6dd9b810732baf1ff758000d6f613a556eb31ba";
+        let (r, s) = (&der[8..74], &der[78..]);
+        let valid = Signature::from_der(&hex::decode(der).unwrap()).unwrap();
+        assert_eq!(valid.to_der(), hex::decode(der).unwrap());
+
+        // (encoding, strict error, whether the lax parser recovers the signature)
+        let cases = [
+            // 3: length of sequence [r, s] uses long form encoding
+            (
+                format!("3081450221{r}0220{s}"),
+                DerError::WrongSequenceLength {
+                    declared: 0x81,
+                    actual: 70,
+                },
+                true,
+            ),
+            // 4: length of sequence [r, s] contains a leading 0
+            (
+                format!("308200450221{r}0220{s}"),
+                DerError::InvalidLength(73),
+                true,
+            ),
+            // 43: indefinite length
+            (
+                format!("30800221{r}0220{s}0000"),
+                DerError::InvalidLength(73),
+                true,
+            ),
+            // 62: length of r uses long form encoding
+            (
+                format!("3046028121{r}0220{s}"),
+                DerError::WrongIntegerLength("R"),
+                true,
+            ),
+            // 110: length of s contains a leading 0
+            (
+                format!("30470221{r}02820020{s}"),
+                DerError::InvalidLength(73),
+                true,
+            ),
+            // 5: length of sequence [r, s] uses 70 instead of 69
+            (
+                format!("30460221{r}0220{s}"),
+                DerError::WrongSequenceLength {
+                    declared: 70,
+                    actual: 69,
+                },
+                true,
+            ),
+            // 20: appending unused 0's to sequence [r, s]
+            (format!("{der}0000"), DerError::InvalidLength(73), true),
+            // 24: appending garbage to sequence [r, s]
+            (
+                format!("3047{der}0004deadbeef"),
+                DerError::InvalidLength(79),
+                false,
+            ),
+            // 112: length of s uses 31 instead of 32
+            (
+                format!("30450221{r}021f{s}"),
+                DerError::WrongIntegerLength("S"),
+                false,
+            ),
+            // 138: dropping value of s
+            (
+                format!("30250221{r}0200"),
+                DerError::ZeroLengthInteger("S"),
+                false,
+            ),
+        ];
+
+        for (der, error, lax_recovers) in cases {
+            let der = hex::decode(der).unwrap();
+            assert_eq!(Signature::from_der(&der), Err(Error::InvalidDer(error)));
+            assert_eq!(
+                Signature::from_der_lax(&der) == Ok(valid.clone()),
+                lax_recovers
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_der_lax() {
+        // (4, 4) with leading zeros, from libsecp256k1's recovery tests
+        let four = Signature {
+            r: BigUint::from(4u32),
+            s: BigUint::from(4u32),
+        };
+        let padded = [
+            format!("30250220{}04020104", "00".repeat(31)),
+            format!("3025020104022000{}04", "00".repeat(30)),
+            format!("30260221{}04020104", "00".repeat(32)),
+        ];
+        for der in padded {
+            let der = hex::decode(der).unwrap();
+            assert_eq!(
+                Signature::from_der(&der),
+                Err(Error::InvalidDer(DerError::ExcessPadding(
+                    if der[3] == 0x01 { "S" } else { "R" }
+                )))
+            );
+            assert_eq!(Signature::from_der_lax(&der), Ok(four.clone()));
+        }
+
+        // negative integers are read as unsigned
+        let der = hex::decode("3006020180020104").unwrap();
+        assert_eq!(
+            Signature::from_der(&der),
+            Err(Error::InvalidDer(DerError::NegativeInteger("R")))
+        );
+        assert_eq!(
+            Signature::from_der_lax(&der).unwrap().r,
+            BigUint::from(0x80u32)
+        );
+
+        // (n + 4, 4) doesn't fit the scalar range, which gives (0, 0)
+        let der = hex::decode(
+            "3026022100fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364145020104",
+        )
+        .unwrap();
+        let zero = Signature {
+            r: BigUint::zero(),
+            s: BigUint::zero(),
+        };
+        assert!(Signature::from_der(&der).is_ok());
+        assert_eq!(Signature::from_der_lax(&der), Ok(zero));
+
+        assert_eq!(
+            Signature::from_der_lax(&hex::decode("3006").unwrap()),
+            Err(Error::InvalidDer(DerError::UnexpectedEnd))
+        );
+    }
+
     #[test]
     fn test_sign_deterministic_k() {
-        let e = BigUint::from(12345u32);
+        let e = PrivateKey::new(&BigUint::from(12345u32)).unwrap();
+
+        let z = sha256_double(b"Programming Bitcoin!");
+
+        let signature = Signature::sign_deterministic(&z, &e);
+        let public_key = Secp256k1Point::compute_public_key(&e);
+        assert!(Signature::verify(&signature, &z, &public_key));
+
+        // same inputs give the same nonce, another message a different one
+        assert_eq!(Signature::sign_deterministic(&z, &e), signature);
+        let other = Signature::sign_deterministic(&sha256_double(b"Programming Bitcoin?"), &e);
+        assert_ne!(other.r, signature.r);
+    }
+
+    #[test]
+    fn test_sign_deterministic_vectors() {
+        let n = Secp256k1Point::n();
+        let cases = [
+            (
+                BigUint::from(1u32),
+                "Satoshi Nakamoto",
+                "3045022100934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8\
+                 02202442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5",
+            ),
+            (
+                BigUint::from(1u32),
+                "All those moments will be lost in time, like tears in rain. Time to die...",
+                "30450221008600dbd41e348fe5c9465ab92d23e3db8b98b873beecd930736488696438cb6b\
+                 0220547fe64427496db33bf66019dacbf0039c04199abb0122918601db38a72cfc21",
+            ),
+            (
+                n.clone() - 1u32,
+                "Satoshi Nakamoto",
+                "3045022100fd567d121db66e382991534ada77a6bd3106f0a1098c231e47993447cd6af2d0\
+                 02206b39cd0eb1bc8603e159ef5c20a5c8ad685a45b06ce9bebed3f153d10d93bed5",
+            ),
+            (
+                BigUint::parse_bytes(
+                    b"f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181",
+                    16,
+                )
+                .unwrap(),
+                "Alan Turing",
+                "304402207063ae83e7f62bbb171798131b4a0564b956930092b33b07b395615d9ec7e15c\
+                 022058dfcc1e00a35e1572f366ffe34ba0fc47db1e7189759b9fb233c5b05ab388ea",
+            ),
+            (
+                BigUint::parse_bytes(
+                    b"e91671c46231f833a6406ccbea0e3e392c76c167bac1cb013f6f1013980455c2",
+                    16,
+                )
+                .unwrap(),
+                "There is a computer disease that anybody who works with computers knows \
+                 about. It's a very serious disease and it interferes completely with the \
+                 work. The trouble with computers is that you 'play' with them!",
+                "3045022100b552edd27580141f3b2a5463048cb7cd3e047b97c9f98076c32dbdf85a68718b\
+                 0220279fa72dd19bfae05577e06c7c0c1900c371fcd5893f7e1d56a37d30174671f6",
+            ),
+        ];
+
+        for (e, message, der) in cases {
+            let e = PrivateKey::new(&e).unwrap();
+            let z = sha256(message.as_bytes());
+            let expected = Signature::from_der(&hex::decode(der).unwrap()).unwrap();
+            let signature = Signature::sign_deterministic(&z, &e);
+
+            assert_eq!(signature, expected);
+        }
+    }
+
+    #[test]
+    fn test_low_s() {
+        let n = Secp256k1Point::n();
+        let e = PrivateKey::new(&BigUint::from(12345u32)).unwrap();
+        let z = sha256_double(b"Programming Bitcoin!");
+        let public_key = Secp256k1Point::compute_public_key(&e);
+
+        for k in 1..20 {
+            let signature = Signature::sign(&z, &e, &Scalar::from(k));
+            assert!(signature.is_low_s());
+            assert!(Signature::verify_low_s(&signature, &z, &public_key));
+
+            let mut high = Signature {
+                r: signature.r.clone(),
+                s: &n - &signature.s,
+            };
+            assert!(!high.is_low_s());
+            assert!(Signature::verify(&high, &z, &public_key));
+            assert!(!Signature::verify_low_s(&high, &z, &public_key));
+
+            high.normalize_s();
+            assert_eq!(high, signature);
+        }
+
+        let boundary = |s: BigUint| Signature {
+            r: BigUint::from(1u32),
+            s,
+        };
+        assert!(boundary(&n >> 1).is_low_s());
+        assert!(!boundary((&n >> 1) + 1u32).is_low_s());
+    }
+
+    #[test]
+    fn test_verify_range() {
+        let n = Secp256k1Point::n();
+        let e = PrivateKey::new(&BigUint::from(12345u32)).unwrap();
+        let z = sha256_double(b"Programming Bitcoin!");
+        let public_key = Secp256k1Point::compute_public_key(&e);
+        let signature = Signature::sign_deterministic(&z, &e);
+        assert!(Signature::verify(&signature, &z, &public_key));
+
+        let out_of_range = [
+            (signature.r.clone(), &signature.s + &n),
+            (&signature.r + &n, signature.s.clone()),
+            (BigUint::zero(), signature.s.clone()),
+            (signature.r.clone(), BigUint::zero()),
+        ];
+        for (r, s) in out_of_range {
+            assert!(!Signature::verify(&Signature { r, s }, &z, &public_key));
+        }
+    }
+
+    #[test]
+    fn test_sign_recoverable() {
+        // (private key, message, recovery id, r || s, compressed public key)
+        let cases = [
+            (
+                "01",
+                "Satoshi Nakamoto",
+                1,
+                "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8\
+                 2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5",
+                "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            ),
+            (
+                "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140",
+                "Satoshi Nakamoto",
+                0,
+                "fd567d121db66e382991534ada77a6bd3106f0a1098c231e47993447cd6af2d0\
+                 6b39cd0eb1bc8603e159ef5c20a5c8ad685a45b06ce9bebed3f153d10d93bed5",
+                "0379be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            ),
+            (
+                "f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181",
+                "Alan Turing",
+                0,
+                "7063ae83e7f62bbb171798131b4a0564b956930092b33b07b395615d9ec7e15c\
+                 58dfcc1e00a35e1572f366ffe34ba0fc47db1e7189759b9fb233c5b05ab388ea",
+                "0292df7b245b81aa637ab4e867c8d511008f79161a97d64f2ac709600352f7acbc",
+            ),
+            (
+                "3039",
+                "Programming Bitcoin!",
+                0,
+                "adb4c3f1ad863d1b4ba54a1d3fd498a9256cc194cac22580d6780d9772ffc405\
+                 34d984ca54094406e3337448db7e1213b6d24b465ca68b84585a8ac447dcdbed",
+                "03f01d6b9018ab421dd410404cb869072065522bf85734008f105cf385a023a80f",
+            ),
+            (
+                "e91671c46231f833a6406ccbea0e3e392c76c167bac1cb013f6f1013980455c2",
+                "All those moments will be lost in time, like tears in rain. Time to die...",
+                1,
+                "4c78e24d616c3666fd061c93a974ccc53db17b3871a8c5c432f45576bb7f6adf\
+                 17a6abf2759db4193647f193bf09289abf509c3c29ce636dbcadea143f3d3282",
+                "03567b7512001f3cc4dcb8b8096c046fff571ab07adb2126cd42908f2ff1ca424a",
+            ),
+        ];
+
+        for (e, message, recovery_id, rs, public_key) in cases {
+            let e = PrivateKey::new(&BigUint::parse_bytes(e.as_bytes(), 16).unwrap()).unwrap();
+            let z = sha256(message.as_bytes());
+            let rs = hex::decode(rs).unwrap();
+            let public_key = hex::decode(public_key).unwrap();
+
+            let (signature, id) = Signature::sign_recoverable(&z, &e);
+            assert_eq!(id, recovery_id);
+            assert_eq!(signature.to_compact(id, true).unwrap()[1..], rs[..]);
+            assert_eq!(signature.recover(&z, id).to_sec(true), public_key);
+
+            // the other ids give different keys or none at all
+            for other in (0..4).filter(|other| *other != id) {
+                if let Ok(key) = signature.try_recover(&z, other) {
+                    assert_ne!(key.to_sec(true), public_key);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_recover_x_above_n() {
+        // R with x >= n, where r = x - n and the recovery id has bit 1 set
+        let n = Secp256k1Point::n();
+        let (r, point) = (1u32..)
+            .find_map(|t| {
+                let mut sec = vec![0x02];
+                sec.extend_from_slice(&int_2_octets::<32>(&n + t));
+                Secp256k1Point::from_sec(&sec)
+                    .ok()
+                    .map(|point| (BigUint::from(t), point))
+            })
+            .unwrap();
+
+        let signature = Signature {
+            r: r.clone(),
+            s: BigUint::from(987654321u32),
+        };
+        let z = sha256(b"Programming Bitcoin!");
+
+        // P = r^-1 * (s * R - z * G)
+        let z_int = BigUint::from_bytes_be(&z);
+        let expected = (point.scale(signature.s.clone())
+            + -Secp256k1Point::generator().scale(z_int))
+        .scale(r.modpow(&Secp256k1Point::n_minus_2(), &n));
+
+        assert_eq!(signature.recover(&z, 2), expected);
+        assert!(Signature::verify(&signature, &z, &expected));
+        assert_eq!(
+            signature.try_recover(&z, 4),
+            Err(Error::InvalidRecoveryId(4))
+        );
+    }
+
+    #[test]
+    fn test_compact() {
+        let e = PrivateKey::new(&BigUint::from(12345u32)).unwrap();
+        let z = sha256(b"Programming Bitcoin!");
+        let (signature, recovery_id) = Signature::sign_recoverable(&z, &e);
+
+        for compressed in [false, true] {
+            let compact = signature.to_compact(recovery_id, compressed).unwrap();
+            assert_eq!(
+                compact[0],
+                27 + recovery_id + if compressed { 4 } else { 0 }
+            );
+            assert_eq!(
+                Signature::from_compact(&compact),
+                Ok((signature.clone(), recovery_id, compressed))
+            );
+        }
+
+        for recovery_id in [4, 255] {
+            assert_eq!(
+                signature.to_compact(recovery_id, true),
+                Err(Error::InvalidRecoveryId(recovery_id))
+            );
+        }
+
+        // r and s outside [1, n - 1] aren't cut down to 32 bytes
+        let n = Secp256k1Point::n();
+        let wide = Signature {
+            r: &signature.r + (BigUint::from(1u32) << 256),
+            s: signature.s.clone(),
+        };
+        let s_n = Signature {
+            r: signature.r.clone(),
+            s: n,
+        };
+        for out_of_range in [wide, s_n] {
+            assert_eq!(
+                out_of_range.to_compact(recovery_id, true),
+                Err(Error::SignatureOutOfRange)
+            );
+        }
+
+        let mut compact = signature.to_compact(recovery_id, true).unwrap();
+        assert_eq!(
+            Signature::from_compact(&compact[..64]),
+            Err(Error::InvalidCompactLength(64))
+        );
+        compact[0] = 35;
+        assert_eq!(
+            Signature::from_compact(&compact),
+            Err(Error::InvalidCompactHeader(35))
+        );
 
-        let z = sha256_double("Programming Bitcoin!");
+        let zero = Signature {
+            r: BigUint::zero(),
+            s: signature.s.clone(),
+        };
+        assert_eq!(zero.try_recover(&z, 0), Err(Error::RecoveryFailed));
     }
 }
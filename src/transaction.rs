@@ -0,0 +1,453 @@
+#![allow(dead_code)]
+
+use crate::encoding::{serialize, Decodable, DecodeLimits, Encodable};
+use crate::error::{DecodeError, Result};
+use crate::hash::{Txid, Wtxid};
+use std::io::{self, Read, Write};
+
+/// Reference to the output `vout` of the transaction `txid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutPoint {
+    pub txid: Txid,
+    pub vout: u32,
+}
+
+impl OutPoint {
+    /// Previous output of coinbase inputs, which spend nothing.
+    pub const NULL: OutPoint = OutPoint {
+        txid: Txid::from_byte_array([0u8; 32]),
+        vout: u32::MAX,
+    };
+
+    pub fn new(txid: Txid, vout: u32) -> OutPoint {
+        OutPoint { txid, vout }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == OutPoint::NULL
+    }
+}
+
+/// Weight units per non-witness byte (BIP141).
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+
+/// Transaction input. The witness, a stack of byte vectors that is empty
+/// for legacy inputs, is serialized apart from the input (BIP144).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxIn {
+    pub previous_output: OutPoint,
+    pub script_sig: Vec<u8>,
+    pub sequence: u32,
+    pub witness: Vec<Vec<u8>>,
+}
+
+impl TxIn {
+    /// Input with empty script_sig and witness and the final sequence
+    /// 0xffffffff.
+    pub fn new(previous_output: OutPoint) -> TxIn {
+        TxIn {
+            previous_output,
+            script_sig: Vec::new(),
+            sequence: u32::MAX,
+            witness: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxOut {
+    /// Amount in satoshis.
+    pub value: u64,
+    pub script_pubkey: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub version: i32,
+    pub inputs: Vec<TxIn>,
+    pub outputs: Vec<TxOut>,
+    pub locktime: u32,
+}
+
+impl Transaction {
+    /// Double SHA256 of the transaction without witnesses, which signatures
+    /// can't malleate.
+    pub fn txid(&self) -> Txid {
+        let mut bytes = Vec::new();
+        self.encode_legacy(&mut bytes)
+            .expect("writing to a Vec doesn't fail");
+        Txid::hash(&bytes)
+    }
+
+    /// Double SHA256 of the full serialization, the same as the txid when
+    /// no input has a witness.
+    pub fn wtxid(&self) -> Wtxid {
+        Wtxid::hash(&serialize(self))
+    }
+
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    /// Size of the serialization without witnesses.
+    pub fn base_size(&self) -> usize {
+        self.encode_legacy(&mut io::sink())
+            .expect("writing to a sink doesn't fail")
+    }
+
+    /// Size of the serialization with witnesses.
+    pub fn total_size(&self) -> usize {
+        self.encode(&mut io::sink())
+            .expect("writing to a sink doesn't fail")
+    }
+
+    /// BIP141 weight: 4 units per base byte and 1 per witness byte.
+    pub fn weight(&self) -> usize {
+        self.base_size() * (WITNESS_SCALE_FACTOR - 1) + self.total_size()
+    }
+
+    /// Virtual size in vbytes, the weight divided by 4 rounding up.
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(WITNESS_SCALE_FACTOR)
+    }
+
+    /// A coinbase has a single input spending the null outpoint.
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_output.is_null()
+    }
+
+    // Version, inputs, outputs and locktime
+    fn encode_legacy<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+        Ok(self.version.encode(writer)?
+            + self.inputs.encode(writer)?
+            + self.outputs.encode(writer)?
+            + self.locktime.encode(writer)?)
+    }
+}
+
+impl Encodable for OutPoint {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+        Ok(self.txid.encode(writer)? + self.vout.encode(writer)?)
+    }
+}
+
+impl Decodable for OutPoint {
+    fn decode<R: Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<Self> {
+        Ok(OutPoint {
+            txid: Txid::decode(reader, limits)?,
+            vout: u32::decode(reader, limits)?,
+        })
+    }
+}
+
+/// Without the witness.
+impl Encodable for TxIn {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+        Ok(self.previous_output.encode(writer)?
+            + self.script_sig.encode(writer)?
+            + self.sequence.encode(writer)?)
+    }
+}
+
+impl Decodable for TxIn {
+    fn decode<R: Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<Self> {
+        Ok(TxIn {
+            previous_output: OutPoint::decode(reader, limits)?,
+            script_sig: Vec::decode(reader, limits)?,
+            sequence: u32::decode(reader, limits)?,
+            witness: Vec::new(),
+        })
+    }
+}
+
+impl Encodable for TxOut {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+        Ok(self.value.encode(writer)? + self.script_pubkey.encode(writer)?)
+    }
+}
+
+impl Decodable for TxOut {
+    fn decode<R: Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<Self> {
+        Ok(TxOut {
+            value: u64::decode(reader, limits)?,
+            script_pubkey: Vec::decode(reader, limits)?,
+        })
+    }
+}
+
+/// Legacy serialization, or the BIP144 one when some input has a witness:
+/// a 0x00 marker and 0x01 flag after the version and the witnesses of all
+/// inputs before the locktime.
+impl Encodable for Transaction {
+    fn encode<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<usize> {
+        if !self.has_witness() {
+            return self.encode_legacy(writer);
+        }
+
+        let mut written = self.version.encode(writer)?;
+        written += [0x00u8, 0x01].encode(writer)?;
+        written += self.inputs.encode(writer)?;
+        written += self.outputs.encode(writer)?;
+        for input in &self.inputs {
+            written += input.witness.encode(writer)?;
+        }
+        Ok(written + self.locktime.encode(writer)?)
+    }
+}
+
+/// Reads both formats. An empty input list is taken as the segwit marker,
+/// like Bitcoin Core does.
+impl Decodable for Transaction {
+    fn decode<R: Read + ?Sized>(reader: &mut R, limits: &DecodeLimits) -> Result<Self> {
+        let version = i32::decode(reader, limits)?;
+        let inputs = Vec::<TxIn>::decode(reader, limits)?;
+        if !inputs.is_empty() {
+            return Ok(Transaction {
+                version,
+                inputs,
+                outputs: Vec::decode(reader, limits)?,
+                locktime: u32::decode(reader, limits)?,
+            });
+        }
+
+        let flag = u8::decode(reader, limits)?;
+        if flag != 0x01 {
+            return Err(DecodeError::UnsupportedSegwitFlag(flag).into());
+        }
+
+        let mut tx = Transaction {
+            version,
+            inputs: Vec::decode(reader, limits)?,
+            outputs: Vec::decode(reader, limits)?,
+            locktime: 0,
+        };
+        for input in tx.inputs.iter_mut() {
+            input.witness = Vec::decode(reader, limits)?;
+        }
+        if !tx.has_witness() {
+            return Err(DecodeError::SuperfluousWitness.into());
+        }
+        tx.locktime = u32::decode(reader, limits)?;
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::deserialize;
+
+    // Coinbase of the genesis block
+    const GENESIS_COINBASE: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    // Programming Bitcoin, chapter 5
+    const BOOK_TX: &str = "0100000001813f79011acb80925dfe69b3def355fe914bd1d96a3f5f71bf8303c6a989c7d1000000006b483045022100ed81ff192e75a3fd2304004dcadb746fa5e24c5031ccfcf21320b0277457c98f02207a986d955c6e0cb35d446a89d3f56100f4d7f67801c31967743a9c8e10615bed01210349fc4e631e3624a545de3f89f5d8684c7b8138bd94bdd531d2e213bf016b278afeffffff02a135ef01000000001976a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac99c39800000000001976a9141c4bc762dd5423e332166702cb75f40df79fea1288ac19430600";
+
+    // Mainnet transactions from rust-bitcoin's tests
+    const P2PKH_TX: &str = "0100000001a15d57094aa7a21a28cb20b59aab8fc7d1149a3bdbcddba9c622e4f5f6a99ece010000006c493046022100f93bb0e7d8db7bd46e40132d1f8242026e045f03a0efe71bbb8e3f475e970d790221009337cd7f1f929f00cc6ff01f03729b069a7c21b59b1736ddfee5db5946c5da8c0121033b9b137ee87d5a812d6f506efdd37f0affa7ffc310711c06c7f3e097c9447c52ffffffff0100e1f505000000001976a9140389035a9225b3839e2bbf32d826a1e222031fd888ac00000000";
+    const OP_RETURN_TX: &str = "01000000010c7196428403d8b0c88fcb3ee8d64f56f55c8973c9ab7dd106bb4f3527f5888d000000006a4730440220503a696f55f2c00eee2ac5e65b17767cd88ed04866b5637d3c1d5d996a70656d02202c9aff698f343abb6d176704beda63fcdec503133ea4f6a5216b7f925fa9910c0121024d89b5a13d6521388969209df27a8469bd565aff10e8d42cef931fad5121bfb8ffffffff02b825b404000000001976a914ef79e7ee9fff98bcfd08473d2b76b02a48f8c69088ac0000000000000000296a2732363030393438363937313732333132373633313032313332353630353838373931323132373000000000";
+
+    // Mainnet P2WPKH spend
+    const SEGWIT_TX: &str = "02000000000101595895ea20179de87052b4046dfe6fd515860505d6511a9004cf12a1f93cac7c0100000000ffffffff01deb807000000000017a9140f3444e271620c736808aa7b33e370bd87cb5a078702483045022100fb60dad8df4af2841adc0346638c16d0b8035f5e3f3753b88db122e70c79f9370220756e6633b17fd2710e626347d28d60b0a2d6cbb41de51740644b9fb3ba7751040121028fa937ca8cba2197a37c007176ed8941055d3bcb8627d085e94553e62f057dcc00000000";
+
+    #[test]
+    fn test_round_trip() {
+        let cases = [
+            (
+                GENESIS_COINBASE,
+                "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+            ),
+            (
+                BOOK_TX,
+                "452c629d67e41baec3ac6f04fe744b4b9617f8f859c63b3002f8684e7a4fee03",
+            ),
+            (
+                P2PKH_TX,
+                "a6eab3c14ab5272a58a5ba91505ba1a4b6d7a3a9fcbd187b6cd99a7b6d548cb7",
+            ),
+            (
+                OP_RETURN_TX,
+                "971ed48a62c143bbd9c87f4bafa2ef213cfa106c6e140f111931d0be307468dd",
+            ),
+        ];
+
+        for (raw, txid) in cases {
+            let bytes = hex::decode(raw).unwrap();
+            let tx: Transaction = deserialize(&bytes).unwrap();
+
+            assert_eq!(serialize(&tx), bytes);
+            assert_eq!(tx.txid().to_string(), txid);
+            assert_eq!(tx.wtxid().to_string(), txid);
+            assert_eq!(tx.base_size(), bytes.len());
+            assert_eq!(tx.total_size(), bytes.len());
+            assert_eq!(tx.weight(), 4 * bytes.len());
+            assert_eq!(tx.vsize(), bytes.len());
+        }
+    }
+
+    #[test]
+    fn test_fields() {
+        let tx: Transaction = deserialize(&hex::decode(BOOK_TX).unwrap()).unwrap();
+
+        assert_eq!(tx.version, 1);
+        assert_eq!(tx.locktime, 410393);
+        assert!(!tx.is_coinbase());
+
+        assert_eq!(tx.inputs.len(), 1);
+        let input = &tx.inputs[0];
+        assert_eq!(
+            input.previous_output.txid.to_string(),
+            "d1c789a9c60383bf715f3f6ad9d14b91fe55f3deb369fe5d9280cb1a01793f81"
+        );
+        assert_eq!(input.previous_output.vout, 0);
+        assert_eq!(input.script_sig.len(), 0x6b);
+        assert_eq!(input.sequence, 0xfffffffe);
+
+        assert_eq!(tx.outputs.len(), 2);
+        assert_eq!(tx.outputs[0].value, 32454049);
+        assert_eq!(
+            hex::encode(&tx.outputs[0].script_pubkey),
+            "76a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac"
+        );
+        assert_eq!(tx.outputs[1].value, 10011545);
+
+        let coinbase: Transaction = deserialize(&hex::decode(GENESIS_COINBASE).unwrap()).unwrap();
+        assert!(coinbase.is_coinbase());
+        assert_eq!(coinbase.inputs[0].previous_output, OutPoint::NULL);
+        assert_eq!(coinbase.outputs[0].value, 50 * 100_000_000);
+    }
+
+    #[test]
+    fn test_build() {
+        let previous = Txid::hash(b"previous");
+        let mut input = TxIn::new(OutPoint::new(previous, 1));
+        input.script_sig = vec![0x51];
+        let tx = Transaction {
+            version: 2,
+            inputs: vec![input],
+            outputs: vec![TxOut {
+                value: 1000,
+                script_pubkey: vec![0x6a],
+            }],
+            locktime: 0,
+        };
+
+        let bytes = serialize(&tx);
+        assert_eq!(bytes.len(), 4 + 1 + (36 + 2 + 4) + 1 + (8 + 2) + 4);
+        assert_eq!(bytes[5..37], previous.to_byte_array());
+        assert_eq!(deserialize::<Transaction>(&bytes), Ok(tx));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let bytes = hex::decode(P2PKH_TX).unwrap();
+
+        assert_eq!(
+            deserialize::<Transaction>(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::UnexpectedEnd.into())
+        );
+        assert_eq!(
+            deserialize::<Transaction>(&[&bytes[..], &[0]].concat()),
+            Err(DecodeError::TrailingBytes(1).into())
+        );
+
+        // script_sig length 0xfd0000 instead of 0x6c
+        let mut non_minimal = bytes[..41].to_vec();
+        non_minimal.extend_from_slice(&[0xfd, 0x6c, 0x00]);
+        non_minimal.extend_from_slice(&bytes[42..]);
+        assert_eq!(
+            deserialize::<Transaction>(&non_minimal),
+            Err(DecodeError::NonMinimalCompactSize(0x6c).into())
+        );
+    }
+
+    #[test]
+    fn test_segwit() {
+        let bytes = hex::decode(SEGWIT_TX).unwrap();
+        let tx: Transaction = deserialize(&bytes).unwrap();
+
+        assert!(tx.has_witness());
+        assert_eq!(serialize(&tx), bytes);
+        assert_eq!(tx.inputs[0].script_sig, Vec::<u8>::new());
+        assert_eq!(tx.inputs[0].witness.len(), 2);
+        assert_eq!(tx.inputs[0].witness[0].len(), 72);
+        assert_eq!(
+            hex::encode(&tx.inputs[0].witness[1]),
+            "028fa937ca8cba2197a37c007176ed8941055d3bcb8627d085e94553e62f057dcc"
+        );
+
+        assert_eq!(
+            tx.txid().to_string(),
+            "f5864806e3565c34d1b41e716f72609d00b55ea5eac5b924c9719a842ef42206"
+        );
+        assert_eq!(
+            tx.wtxid().to_string(),
+            "80b7d8a82d5d5bf92905b06f2014dd699e03837ca172e3a59d51426ebbe3e7f5"
+        );
+        assert_eq!(tx.total_size(), bytes.len());
+        assert_eq!(tx.base_size(), 83);
+        assert_eq!(tx.weight(), 442);
+        assert_eq!(tx.vsize(), 111);
+
+        // without witnesses it is the legacy transaction with the same txid
+        let mut stripped = tx.clone();
+        stripped.inputs[0].witness.clear();
+        assert!(!stripped.has_witness());
+        assert_eq!(stripped.total_size(), 83);
+        assert_eq!(stripped.txid(), tx.txid());
+        assert_eq!(stripped.wtxid().to_byte_array(), tx.txid().to_byte_array());
+    }
+
+    #[test]
+    fn test_weight() {
+        // Mainnet transactions and weights from rust-bitcoin's tests
+        let cases = [
+            // one P2WPKH input
+            ("020000000001018a763b78d3e17acea0625bf9e52b0dc1beb2241b2502185348ba8ff4a253176e0100000000ffffffff0280d725000000000017a914c07ed639bd46bf7087f2ae1dfde63b815a5f8b488767fda20300000000160014869ec8520fa2801c8a01bfdd2e82b19833cd0daf02473044022016243edad96b18c78b545325aaff80131689f681079fb107a67018cb7fb7830e02205520dae761d89728f73f1a7182157f6b5aecf653525855adb7ccb998c8e6143b012103b9489bde92afbcfa85129a82ffa512897105d1a27ad9806bded27e0532fc84e700000000", 565),
+            // one P2WSH input
+            ("01000000000101a3ccad197118a2d4975fadc47b90eacfdeaf8268adfdf10ed3b4c3b7e1ad14530300000000ffffffff0200cc5501000000001976a91428ec6f21f4727bff84bb844e9697366feeb69f4d88aca2a5100d00000000220020701a8d401c84fb13e6baf169d59684e17abd9fa216c8cc5b9fc63d622ff8c58d04004730440220548f11130353b3a8f943d2f14260345fc7c20bde91704c9f1cbb5456355078cd0220383ed4ed39b079b618bcb279bbc1f2ca18cb028c4641cb522c9c5868c52a0dc20147304402203c332ecccb3181ca82c0600520ee51fee80d3b4a6ab110945e59475ec71e44ac0220679a11f3ca9993b04ccebda3c834876f353b065bb08f50076b25f5bb93c72ae1016952210375e00eb72e29da82b89367947f29ef34afb75e8654f6ea368e0acdfd92976b7c2103a1b26313f430c4b15bb1fdce663207659d8cac749a0e53d70eff01874496feff2103c96d495bfdd5ba4145e3e046fee45e84a8a48ad05bd8dbb395c011a32cf9f88053ae00000000", 766),
+            // one P2WPKH and two P2PKH inputs
+            ("010000000001036b6b6ac7e34e97c53c1cc74c99c7948af2e6aac75d8778004ae458d813456764000000006a473044022001deec7d9075109306320b3754188f81a8236d0d232b44bc69f8309115638b8f02204e17a5194a519cf994d0afeea1268740bdc10616b031a521113681cc415e815c012103488d3272a9fad78ee887f0684cb8ebcfc06d0945e1401d002e590c7338b163feffffffffc75bd7aa6424aee972789ec28ba181254ee6d8311b058d165bd045154d7660b0000000006b483045022100c8641bcbee3e4c47a00417875015d8c5d5ea918fb7e96f18c6ffe51bc555b401022074e2c46f5b1109cd79e39a9aa203eadd1d75356415e51d80928a5fb5feb0efee0121033504b4c6dfc3a5daaf7c425aead4c2dbbe4e7387ce8e6be2648805939ecf7054ffffffff494df3b205cd9430a26f8e8c0dc0bb80496fbc555a524d6ea307724bc7e60eee0100000000ffffffff026d861500000000001976a9145c54ed1360072ebaf56e87693b88482d2c6a101588ace407000000000000160014761e31e2629c6e11936f2f9888179d60a5d4c1f900000247304402201fa38a67a63e58b67b6cfffd02f59121ca1c8a1b22e1efe2573ae7e4b4f06c2b022002b9b431b58f6e36b3334fb14eaecee7d2f06967a77ef50d8d5f90dda1057f0c01210257dc6ce3b1100903306f518ee8fa113d778e403f118c080b50ce079fba40e09a00000000", 1755),
+            // one P2TR input
+            ("01000000000101b5cee87f1a60915c38bb0bc26aaf2b67be2b890bbc54bb4be1e40272e0d2fe0b0000000000ffffffff025529000000000000225120106daad8a5cb2e6fc74783714273bad554a148ca2d054e7a19250e9935366f3033760000000000002200205e6d83c44f57484fd2ef2a62b6d36cdcd6b3e06b661e33fd65588a28ad0dbe060141df9d1bfce71f90d68bf9e9461910b3716466bfe035c7dbabaa7791383af6c7ef405a3a1f481488a91d33cd90b098d13cb904323a3e215523aceaa04e1bb35cdb0100000000", 617),
+            // one P2PKH input
+            ("0100000001c336895d9fa674f8b1e294fd006b1ac8266939161600e04788c515089991b50a030000006a47304402204213769e823984b31dcb7104f2c99279e74249eacd4246dabcf2575f85b365aa02200c3ee89c84344ae326b637101a92448664a8d39a009c8ad5d147c752cbe112970121028b1b44b4903c9103c07d5a23e3c7cf7aeb0ba45ddbd2cfdce469ab197381f195fdffffff040000000000000000536a4c5058325bb7b7251cf9e36cac35d691bd37431eeea426d42cbdecca4db20794f9a4030e6cb5211fabf887642bcad98c9994430facb712da8ae5e12c9ae5ff314127d33665000bb26c0067000bb0bf00322a50c300000000000017a9145ca04fdc0a6d2f4e3f67cfeb97e438bb6287725f8750c30000000000001976a91423086a767de0143523e818d4273ddfe6d9e4bbcc88acc8465003000000001976a914c95cbacc416f757c65c942f9b6b8a20038b9b12988ac00000000", 1396),
+        ];
+
+        for (raw, weight) in cases {
+            let bytes = hex::decode(raw).unwrap();
+            let tx: Transaction = deserialize(&bytes).unwrap();
+
+            assert_eq!(serialize(&tx), bytes);
+            assert_eq!(tx.total_size(), bytes.len());
+            assert_eq!(tx.weight(), weight);
+            assert_eq!(tx.vsize(), weight.div_ceil(4));
+        }
+    }
+
+    #[test]
+    fn test_segwit_decode_errors() {
+        let bytes = hex::decode(SEGWIT_TX).unwrap();
+
+        let mut flag = bytes.clone();
+        flag[5] = 0x02;
+        assert_eq!(
+            deserialize::<Transaction>(&flag),
+            Err(DecodeError::UnsupportedSegwitFlag(0x02).into())
+        );
+
+        // the witness of the only input replaced by an empty stack
+        let tx: Transaction = deserialize(&bytes).unwrap();
+        let base = tx.base_size();
+        let empty = [&bytes[..base - 2], &[0x00], &bytes[bytes.len() - 4..]].concat();
+        assert_eq!(
+            deserialize::<Transaction>(&empty),
+            Err(DecodeError::SuperfluousWitness.into())
+        );
+
+        assert_eq!(
+            deserialize::<Transaction>(&bytes[..bytes.len() - 10]),
+            Err(DecodeError::UnexpectedEnd.into())
+        );
+    }
+}